@@ -1,3 +1,7 @@
+use crate::engine::bitboard::{self, Bitboard};
+use crate::engine::{self, figures};
+use std::ops::{Add, AddAssign, Mul, Sub};
+
 // Values from the PeSTO evaluation function: https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function
 const VALUE_MG_PAWN: i32 = 82;
 const VALUE_MG_KNIGHT: i32 = 337;
@@ -13,6 +17,7 @@ const VALUE_EG_QUEEN: i32 = 936;
 
 const VALUE_KING: i32 = 0;
 
+/// Middle game values of the blank figures, indexed by [`crate::engine::figures::Figure`].
 pub static MG_VALUES: [i32; 10] = [
     0,
     VALUE_KING,
@@ -26,6 +31,7 @@ pub static MG_VALUES: [i32; 10] = [
     VALUE_MG_PAWN,
 ];
 
+/// End game values of the blank figures, indexed by [`crate::engine::figures::Figure`].
 pub static EG_VALUES: [i32; 10] = [
     0,
     VALUE_KING,
@@ -114,6 +120,7 @@ const EG_KING_TABLE: [i32; 64] = [
     -150, -150, -150, -150, -200, -200, -300, -200, -200, -200, -200, -200, -200, -300,
 ];
 
+/// Middle game piece-square tables of the blank figures, indexed by [`crate::engine::figures::Figure`] and the position.
 pub static MG_TABLES: [[i32; 64]; 10] = [
     [0; 64],
     MG_KING_TABLE,
@@ -127,6 +134,7 @@ pub static MG_TABLES: [[i32; 64]; 10] = [
     MG_PAWN_TABLE,
 ];
 
+/// End game piece-square tables of the blank figures, indexed by [`crate::engine::figures::Figure`] and the position.
 pub static EG_TABLES: [[i32; 64]; 10] = [
     [0; 64],
     EG_KING_TABLE,
@@ -140,4 +148,449 @@ pub static EG_TABLES: [[i32; 64]; 10] = [
     EG_PAWN_TABLE,
 ];
 
+/// How much a figure counts towards the middle game. All figures of the initial board sum up to 24.
 pub const GAME_PHASE_INC: [i32; 10] = [0, 0, 4, 1, 0, 1, 0, 2, 0, 0];
+
+/// A middle game and an end game value, that are blended by the game phase like the piece-square tables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+
+    fn mul(self, factor: i32) -> Score {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+// Weights of the terms beyond the material and the piece-square tables, each for the middle game and the end game.
+const DOUBLED_PAWN: Score = Score::new(-10, -25);
+const ISOLATED_PAWN: Score = Score::new(-12, -15);
+const BACKWARD_PAWN: Score = Score::new(-8, -12);
+/// Indexed by the rank of the pawn, seen from its own side.
+const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(2, 8),
+    Score::new(4, 12),
+    Score::new(10, 25),
+    Score::new(25, 50),
+    Score::new(50, 90),
+    Score::new(80, 140),
+    Score::new(0, 0),
+];
+
+/// The value of each reachable position, indexed by [`figures::Figure`].
+const MOBILITY: [Score; 10] = [
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(1, 2),
+    Score::new(4, 4),
+    Score::new(0, 0),
+    Score::new(3, 4),
+    Score::new(0, 0),
+    Score::new(2, 4),
+    Score::new(0, 0),
+    Score::new(0, 0),
+];
+/// The number of reachable positions that is worth nothing, indexed by [`figures::Figure`].
+const MOBILITY_BASE: [i32; 10] = [0, 0, 14, 4, 0, 7, 0, 7, 0, 0];
+
+const BISHOP_PAIR: Score = Score::new(30, 50);
+const ROOK_OPEN_FILE: Score = Score::new(40, 15);
+const ROOK_SEMI_OPEN_FILE: Score = Score::new(20, 10);
+
+/// For each pawn of the own side directly in front of the king, and two positions in front of the king.
+const PAWN_SHIELD: [Score; 2] = [Score::new(15, 0), Score::new(8, 0)];
+/// How much a figure attacking the positions around the king counts, indexed by [`figures::Figure`].
+const KING_ATTACK_UNITS: [i32; 10] = [0, 0, 5, 2, 0, 2, 0, 3, 0, 0];
+/// Multiplied with the square of the attack units, if at least two figures attack the king.
+const KING_ATTACK: Score = Score::new(-1, 0);
+
+const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+
+/// The figures of one side as bitboards.
+struct Side {
+    white: bool,
+    king: u8,
+    pawns: Bitboard,
+    bishops: u32,
+    all: Bitboard,
+    /// All positions a pawn of the side catches on.
+    pawn_attacks: Bitboard,
+}
+
+impl Side {
+    fn new(figures: &engine::Positions, white: bool) -> Side {
+        let mut side = Side {
+            white,
+            king: 0,
+            pawns: 0,
+            bishops: 0,
+            all: 0,
+            pawn_attacks: 0,
+        };
+
+        for &(figure, x, y) in figures.iter().filter(|figure| figure.0 != 255) {
+            let square = bitboard::to_square(x, y);
+            side.all |= bitboard::bit(x, y);
+            match figures::colored_figure_to_blank_figure(figure) {
+                figures::KING => side.king = square,
+                figures::BISHOP => side.bishops += 1,
+                figures::PAWN => {
+                    side.pawns |= bitboard::bit(x, y);
+                    side.pawn_attacks |= bitboard::PAWN_ATTACKS[!white as usize][square as usize];
+                }
+                _ => (),
+            }
+        }
+
+        side
+    }
+}
+
+/// Returns the positions of the file and of the files next to it.
+fn get_adjacent_files(x: u8) -> Bitboard {
+    let file = FILE_A << (x - 1);
+    file | ((file << 1) & !FILE_A) | ((file >> 1) & !(FILE_A << 7))
+}
+
+/// Returns the positions of all ranks in front of the given rank, seen from the given side.
+fn get_ranks_in_front(y: u8, white: bool) -> Bitboard {
+    if white {
+        u64::MAX.checked_shl(8 * y as u32).unwrap_or(0)
+    } else {
+        (1 << (8 * (y - 1))) - 1
+    }
+}
+
+/// Returns the positions the figure reaches from the square.
+fn get_attacks(figure: figures::Figure, square: u8, occupied: Bitboard) -> Bitboard {
+    match figure {
+        figures::KNIGHT => bitboard::KNIGHT_ATTACKS[square as usize],
+        figures::BISHOP => bitboard::bishop_attacks(square, occupied),
+        figures::ROOK => bitboard::rook_attacks(square, occupied),
+        figures::QUEEN => bitboard::queen_attacks(square, occupied),
+        _ => 0,
+    }
+}
+
+/// Doubled, isolated, backward and passed pawns of the side.
+fn get_pawn_structure(side: &Side, other: &Side) -> Score {
+    let mut score = Score::default();
+
+    for square in bitboard::squares(side.pawns) {
+        let (x, y) = bitboard::to_position(square);
+        let file = FILE_A << (x - 1);
+        let adjacent_files = get_adjacent_files(x) & !file;
+        let in_front = get_ranks_in_front(y, side.white);
+
+        if side.pawns & file & in_front != 0 {
+            score += DOUBLED_PAWN;
+        } else if other.pawns & get_adjacent_files(x) & in_front == 0 {
+            let rank = if side.white { y } else { 9 - y };
+            score += PASSED_PAWN[rank as usize - 1];
+        }
+
+        if side.pawns & adjacent_files == 0 {
+            score += ISOLATED_PAWN;
+        } else if side.pawns & adjacent_files & !in_front == 0 {
+            // No pawn can protect it anymore and it can not advance without being caught.
+            let stop = if side.white { square + 8 } else { square - 8 };
+            if other.pawn_attacks & (1 << stop) != 0 {
+                score += BACKWARD_PAWN;
+            }
+        }
+    }
+
+    score
+}
+
+/// The positions the knights, bishops, rooks and queens of the side reach, without the ones of its own figures and
+/// the ones pawns of the other side catch on.
+fn get_mobility(figures: &engine::Positions, side: &Side, other: &Side) -> Score {
+    let mut score = Score::default();
+    let occupied = side.all | other.all;
+
+    for &(figure, x, y) in figures.iter().filter(|figure| figure.0 != 255) {
+        let figure = figures::colored_figure_to_blank_figure(figure) as usize;
+        let attacks = get_attacks(figure as u8, bitboard::to_square(x, y), occupied);
+        let count = (attacks & !side.all & !other.pawn_attacks).count_ones() as i32;
+        score += MOBILITY[figure] * (count - MOBILITY_BASE[figure]);
+    }
+
+    score
+}
+
+fn get_bishop_pair(side: &Side) -> Score {
+    if side.bishops >= 2 {
+        BISHOP_PAIR
+    } else {
+        Score::default()
+    }
+}
+
+/// Rooks on files without pawns, or without pawns of the own side.
+fn get_rook_files(figures: &engine::Positions, side: &Side, other: &Side) -> Score {
+    let mut score = Score::default();
+
+    for &(figure, x, _) in figures.iter().filter(|figure| figure.0 != 255) {
+        if figures::colored_figure_to_blank_figure(figure) != figures::ROOK {
+            continue;
+        }
+
+        let file = FILE_A << (x - 1);
+        if side.pawns & file == 0 {
+            score += if other.pawns & file == 0 {
+                ROOK_OPEN_FILE
+            } else {
+                ROOK_SEMI_OPEN_FILE
+            };
+        }
+    }
+
+    score
+}
+
+/// The pawns in front of the king of the side and the figures of the other side attacking the positions around it.
+fn get_king_safety(side: &Side, other: &Side, other_figures: &engine::Positions) -> Score {
+    let mut score = Score::default();
+    let (x, y) = bitboard::to_position(side.king);
+
+    let files = get_adjacent_files(x);
+    for (distance, shield) in PAWN_SHIELD.iter().enumerate() {
+        let rank = if side.white {
+            y as i32 + 1 + distance as i32
+        } else {
+            y as i32 - 1 - distance as i32
+        };
+        if (1..=8).contains(&rank) {
+            let rank = 0xFF << (8 * (rank - 1));
+            score += *shield * (side.pawns & files & rank).count_ones() as i32;
+        }
+    }
+
+    let zone = bitboard::KING_ATTACKS[side.king as usize] | (1 << side.king);
+    let occupied = side.all | other.all;
+    let mut attackers = 0;
+    let mut units = 0;
+    for &(figure, x, y) in other_figures.iter().filter(|figure| figure.0 != 255) {
+        let figure = figures::colored_figure_to_blank_figure(figure);
+        if get_attacks(figure, bitboard::to_square(x, y), occupied) & zone != 0 {
+            attackers += 1;
+            units += KING_ATTACK_UNITS[figure as usize];
+        }
+    }
+    if attackers >= 2 {
+        score += KING_ATTACK * (units * units);
+    }
+
+    score
+}
+
+/// The evaluation of one side split into its terms, each from the view of the side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Terms {
+    pub material: Score,
+    pub piece_squares: Score,
+    pub pawn_structure: Score,
+    pub mobility: Score,
+    pub bishop_pair: Score,
+    pub rook_files: Score,
+    pub king_safety: Score,
+}
+
+impl Terms {
+    pub fn get_total(&self) -> Score {
+        self.material
+            + self.piece_squares
+            + self.pawn_structure
+            + self.mobility
+            + self.bishop_pair
+            + self.rook_files
+            + self.king_safety
+    }
+}
+
+/// The material and the piece-square table values of the figures of one side.
+fn get_material_and_piece_squares(figures: &engine::Positions, white: bool) -> (Score, Score) {
+    let mut material = Score::default();
+    let mut piece_squares = Score::default();
+
+    for &(figure, x, y) in figures.iter().filter(|figure| figure.0 != 255) {
+        let figure = figures::colored_figure_to_blank_figure(figure) as usize;
+        // The tables start with a8, so they are mirrored for white.
+        let square = if white {
+            8 * (8 - y) + (x - 1)
+        } else {
+            8 * (y - 1) + (x - 1)
+        } as usize;
+
+        material += Score::new(MG_VALUES[figure], EG_VALUES[figure]);
+        piece_squares += Score::new(MG_TABLES[figure][square], EG_TABLES[figure][square]);
+    }
+
+    (material, piece_squares)
+}
+
+/// Returns the terms of white and of black, in this order.
+pub fn get_terms(
+    black_figures: &engine::Positions,
+    white_figures: &engine::Positions,
+) -> [Terms; 2] {
+    let white = Side::new(white_figures, true);
+    let black = Side::new(black_figures, false);
+
+    [
+        (white_figures, &white, black_figures, &black),
+        (black_figures, &black, white_figures, &white),
+    ]
+    .map(|(figures, side, other_figures, other)| {
+        let (material, piece_squares) = get_material_and_piece_squares(figures, side.white);
+        Terms {
+            material,
+            piece_squares,
+            pawn_structure: get_pawn_structure(side, other),
+            mobility: get_mobility(figures, side, other),
+            bishop_pair: get_bishop_pair(side),
+            rook_files: get_rook_files(figures, side, other),
+            king_safety: get_king_safety(side, other, other_figures),
+        }
+    })
+}
+
+/// Returns how far the game is from the end game: 24 with all figures of the initial board, 0 with only kings and pawns.
+pub fn get_game_phase(black_figures: &engine::Positions, white_figures: &engine::Positions) -> i32 {
+    let game_phase: i32 = black_figures
+        .iter()
+        .chain(white_figures)
+        .filter(|figure| figure.0 != 255)
+        .map(|figure| GAME_PHASE_INC[figures::colored_figure_to_blank_figure(figure.0) as usize])
+        .sum();
+
+    // Promotions can lead to more figures than on the initial board.
+    game_phase.min(24)
+}
+
+/// Blends the middle game and the end game value of the score by the game phase.
+pub fn blend(score: Score, game_phase: i32) -> i32 {
+    (score.mg * game_phase + score.eg * (24 - game_phase)) / 24
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai;
+    use crate::engine::fen;
+
+    fn evaluate(fen: &str) -> i32 {
+        let game = fen::from_fen(fen).unwrap();
+        ai::evaluate_board(game.black_figures, game.white_figures)
+    }
+
+    #[test]
+    fn mirrored_boards_have_opposite_values() {
+        assert_eq!(evaluate(fen::INITIAL_FEN), 0);
+
+        for (fen, mirrored) in [
+            (
+                "r1bqk2r/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP3PPP/R1BQKB1R w KQkq - 0 1",
+                "r1bqkb1r/pp3ppp/2n2n2/2pp4/3P4/2N1PN2/PP2BPPP/R1BQK2R w KQkq - 0 1",
+            ),
+            (
+                "8/5k2/8/3P4/8/2K5/8/8 w - - 0 1",
+                "8/8/2k5/8/3p4/8/5K2/8 w - - 0 1",
+            ),
+        ] {
+            assert_eq!(evaluate(fen), -evaluate(mirrored), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn piece_square_tables_are_seen_from_the_own_side() {
+        let piece_squares = |fen: &str, white: bool| {
+            let game = fen::from_fen(fen).unwrap();
+            let [white_terms, black_terms] = get_terms(&game.black_figures, &game.white_figures);
+            if white {
+                white_terms.piece_squares
+            } else {
+                black_terms.piece_squares
+            }
+        };
+
+        // An advanced pawn in the center is better than one that stayed back.
+        assert!(
+            piece_squares("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1", true).mg
+                > piece_squares("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1", true).mg
+        );
+        assert!(
+            piece_squares("4k3/8/8/4p3/8/8/8/4K3 w - - 0 1", false).mg
+                > piece_squares("4k3/8/4p3/8/8/8/8/4K3 w - - 0 1", false).mg
+        );
+    }
+
+    #[test]
+    fn pawn_structure_is_recognized() {
+        let game = fen::from_fen("4k3/8/4P3/8/8/1P6/1P2P3/4K3 w - - 0 1").unwrap();
+        let white = Side::new(&game.white_figures, true);
+        let black = Side::new(&game.black_figures, false);
+
+        // e6 is passed, e2 doubled behind it and both isolated, b2 and b3 are doubled and isolated, b3 is passed.
+        assert_eq!(
+            get_pawn_structure(&white, &black),
+            PASSED_PAWN[5] + PASSED_PAWN[2] + DOUBLED_PAWN * 2 + ISOLATED_PAWN * 4
+        );
+    }
+
+    #[test]
+    fn blocked_pawns_are_not_passed() {
+        let game = fen::from_fen("4k3/8/8/4p3/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let white = Side::new(&game.white_figures, true);
+        let black = Side::new(&game.black_figures, false);
+
+        assert_eq!(get_pawn_structure(&white, &black), ISOLATED_PAWN);
+        assert_eq!(get_pawn_structure(&black, &white), ISOLATED_PAWN);
+    }
+
+    #[test]
+    fn pawns_in_front_of_the_king_are_a_shield() {
+        let game = fen::from_fen("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        let white = Side::new(&game.white_figures, true);
+        let black = Side::new(&game.black_figures, false);
+
+        assert_eq!(
+            get_king_safety(&white, &black, &game.black_figures),
+            PAWN_SHIELD[0] * 3
+        );
+    }
+}
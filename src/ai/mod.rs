@@ -1,335 +1,1029 @@
-mod evaluation;
+pub mod book;
+pub mod evaluation;
+pub mod ordering;
+pub mod tablebase;
+pub mod transposition;
 
 use crate::engine;
-use crate::io;
-use std::io::Write;
+use crate::engine::figures;
+use ordering::Heuristics;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use transposition::{Bound, TranspositionTable};
 
+/// The number of boards [`min_max`] visited since it was last reset, e.g. to calculate the nodes per second.
+pub static NODES: AtomicU64 = AtomicU64::new(0);
+
+/// The deepest search in plies, if the time is not over before.
+pub const MAX_DEPTH: u8 = 64;
+
+/// Where the move of [`turn`] comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveSource {
+    Book,
+    Tablebases,
+    /// The search, also if the player has no move.
+    Search,
+}
+
+/// Searches the best move of the player for `time` and makes it.
+/// If the board is in the opening `book` or in the endgame tables of `config`, the move of the book or the tables is made without searching.
+///
+/// `history` contains all boards of the game, the last one being the current board.
+/// `table` caches the results of the search for the following moves.
+/// `progress` is called with the number of figures (out of 16) that were searched so far in the current depth.
+/// `info` is called after every finished depth, see [`iterative_deepening`].
+/// `config` configures the search, e.g. the number of threads.
+///
+/// Returns the state of the game after the move, the move, the value the player can at least reach after it
+/// (for a move of the book, the value of the board after it, for a move of the tables, the value of the result, see [`tablebase::Wdl::get_value`])
+/// and where the move comes from.
+/// If the player has no move, the state of the current board ([`engine::GameState::CheckMate`] if the player is checkmate,
+/// [`engine::GameState::Draw`] if it is stalemate) and [`engine::moves::NULL_MOVE`] are returned.
+#[allow(clippy::too_many_arguments)]
 pub fn turn(
     board: engine::Board,
     black_figures: engine::Positions,
     white_figures: engine::Positions,
     white: bool,
-    turns: u8,
-    prev_boards: [engine::Board; 10],
-) -> engine::GameState {
-    let depth = turns * 2;
+    time: Duration,
+    history: &[engine::Board],
+    table: &TranspositionTable,
+    config: &SearchConfig,
+    book: Option<&book::Book>,
+    progress: &mut dyn FnMut(u8),
+    info: &mut dyn FnMut(u8, i32, engine::Move),
+) -> (engine::GameState, engine::Move, i32, MoveSource) {
+    let book_move = book.and_then(|book| book.get_move(board, black_figures, white_figures, white));
 
-    let mut best_value = if white { i32::MIN } else { i32::MAX };
-    let mut best_board = (board, black_figures, white_figures);
-    let mut best_move = ((255u8, 1u8, 1u8), (1i8, 1i8));
-
-    let mut alpha = i32::MIN;
-    let mut beta = i32::MAX;
-    if white {
-        println!("       KDRRLLTTBBBBBBBB -> Fortschritt Bedeutung");
+    // The endgame tables are only probed if the book has no move.
+    let result = if let Some(book_move) = book_move {
+        let book_board = engine::move_figure(board, black_figures, white_figures, book_move, white);
+        Some((
+            evaluate_board(book_board.1, book_board.2),
+            book_move,
+            MoveSource::Book,
+        ))
+    } else if let Some((tablebase_move, wdl)) = config
+        .tablebases
+        .and_then(|tablebases| tablebases.probe_root(board, black_figures, white_figures, white))
+    {
+        let value = wdl.get_value(0);
+        Some((
+            if white { value } else { -value },
+            tablebase_move,
+            MoveSource::Tablebases,
+        ))
     } else {
-        println!("          KDRRLLTTBBBBBBBB -> Fortschritt Bedeutung");
-    }
-
-    let mut progress = 0;
-    print_progress(progress, white);
+        iterative_deepening(
+            board,
+            black_figures,
+            white_figures,
+            white,
+            MAX_DEPTH,
+            Some(time),
+            history,
+            table,
+            config,
+            progress,
+            info,
+        )
+        .map(|(value, r#move)| (value, r#move, MoveSource::Search))
+    };
 
-    'outer: for figure in {
-        if white {
-            white_figures
-        } else {
-            black_figures
-        }
-    } {
-        if figure.0 == 255 {
-            progress += 1;
-            print_progress(progress, white);
-            continue;
+    let (best_value, best_move, source) = match result {
+        Some(result) => result,
+        None => {
+            let state = engine::get_game_state(board, black_figures, white_figures, white, history);
+            let value = match state {
+                engine::GameState::CheckMate(_) if white => i32::MIN,
+                engine::GameState::CheckMate(_) => i32::MAX,
+                _ => 0,
+            };
+            return (state, engine::moves::NULL_MOVE, value, MoveSource::Search);
         }
+    };
 
-        let moves = engine::get_valid_moves(board, figure.0, figure.1, figure.2);
-
-        for subset in moves {
-            for r#move in subset {
-                if r#move.0 > 8 {
-                    break;
-                }
+    let best_board = engine::move_figure(board, black_figures, white_figures, best_move, white);
 
-                let (board, black_figures, white_figures) = engine::move_figure(
-                    board,
-                    black_figures,
-                    white_figures,
-                    figure.0,
-                    figure.1,
-                    figure.2,
-                    r#move.0 as u8,
-                    r#move.1 as u8,
-                    white,
-                );
-
-                let different = {
-                    let mut different = [false; 10];
-
-                    for i in 0..64 {
-                        for j in 0..different.len() {
-                            if board[i] != prev_boards[j][i] {
-                                different[j] = true;
-                            }
-                        }
-                        if different.iter().all(|&x| x) {
-                            break;
-                        }
-                    }
+    (
+        engine::get_game_state(best_board.0, best_board.1, best_board.2, !white, history),
+        best_move,
+        best_value,
+        source,
+    )
+}
 
-                    different.iter().all(|&x| x)
-                };
+/// Searches the best move of the player with iterative deepening: the board is searched with a depth of 1, 2, 3, ... plies,
+/// until `max_depth` is reached, `time` is over or the search is stopped with [`SearchConfig::stop`] (see https://www.chessprogramming.org/Iterative_Deepening).
+///
+/// The stop flag of `config` is only read, it is checked every few milliseconds.
+/// Every depth searches the best move of the depth before first, which is read from `table`.
+/// `info` is called with the depth, the value and the best move after every finished depth.
+/// `config` switches the enhancements of [`min_max`] on or off.
+///
+/// With more than one thread in `config`, helper threads search the same board at the same time (Lazy SMP).
+/// Their results are not used, but they fill the shared `table`, so the main thread finds many boards there and searches faster.
+/// The helper threads are stopped when the main thread is finished.
+///
+/// Returns the value and the best move of the deepest finished depth, or [`None`] if the player has no move.
+/// If not even the first depth was finished, the move with the best evaluation right after it is returned.
+#[allow(clippy::too_many_arguments)]
+pub fn iterative_deepening(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+    max_depth: u8,
+    time: Option<Duration>,
+    history: &[engine::Board],
+    table: &TranspositionTable,
+    config: &SearchConfig,
+    progress: &mut dyn FnMut(u8),
+    info: &mut dyn FnMut(u8, i32, engine::Move),
+) -> Option<(i32, engine::Move)> {
+    let start = Instant::now();
+    let finished = AtomicBool::new(false);
+    // The threads are stopped with an own flag, so that the one of the caller is never changed.
+    let stop = AtomicBool::new(false);
+    let caller_config = config;
+    let config = &SearchConfig {
+        stop: Some(&stop),
+        ..*config
+    };
 
-                if !different {
-                    println!(" AI> Detected a repetition move!");
-                    continue;
+    let result = thread::scope(|scope| {
+        // The search is stopped from another thread when the time is over or the caller stops it.
+        if time.is_some() || caller_config.stop.is_some() {
+            let (finished, stop) = (&finished, &stop);
+            scope.spawn(move || {
+                while !finished.load(Ordering::Relaxed) {
+                    if caller_config.is_stopped()
+                        || time.is_some_and(|time| start.elapsed() >= time)
+                    {
+                        stop.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                    thread::sleep(Duration::from_millis(5));
                 }
+            });
+        }
 
-                let value = min_max(
-                    board,
-                    black_figures,
-                    white_figures,
-                    !white,
-                    alpha,
-                    beta,
-                    depth - 1,
-                );
-
-                if white && value > alpha {
-                    alpha = value;
-                    best_value = value;
-                    best_board = (board, black_figures, white_figures);
-                    best_move = (figure, (r#move.0, r#move.1));
-                } else if !white && value < beta {
-                    beta = value;
-                    best_value = value;
-                    best_board = (board, black_figures, white_figures);
-                    best_move = (figure, (r#move.0, r#move.1));
+        for index in 1..config.threads {
+            scope.spawn(move || {
+                let mut heuristics = Heuristics::new();
+                // Half of the helper threads search one ply deeper, so that the threads search different boards.
+                for depth in (1 + index % 2) as u8..=max_depth.max(1) {
+                    if search(
+                        board,
+                        black_figures,
+                        white_figures,
+                        white,
+                        depth,
+                        history,
+                        table,
+                        &mut heuristics,
+                        config,
+                        &mut |_| {},
+                    )
+                    .is_none()
+                    {
+                        break;
+                    }
                 }
+            });
+        }
 
-                if beta <= alpha {
-                    print_progress(16, white);
-                    break 'outer;
-                }
+        // The killer moves and history scores of one depth are also good for the next one.
+        let mut heuristics = Heuristics::new();
+
+        let mut result = None;
+        for depth in 1..=max_depth.max(1) {
+            let (value, best_move) = match search(
+                board,
+                black_figures,
+                white_figures,
+                white,
+                depth,
+                history,
+                table,
+                &mut heuristics,
+                config,
+                progress,
+            ) {
+                Some(value) => value,
+                None => break,
+            };
+            result = Some((value, best_move));
+            info(depth, value, best_move);
+
+            // There is no need to search deeper if a checkmate was found,
+            // and the next depth takes longer than all depths before, so it would not be finished in the time that is left.
+            if value.unsigned_abs() > i32::MAX as u32 - 1000
+                || time.is_some_and(|time| start.elapsed() > time / 2)
+            {
+                break;
             }
         }
 
-        progress += 1;
-        print_progress(progress, white);
-    }
+        finished.store(true, Ordering::Relaxed);
+        stop.store(true, Ordering::Relaxed);
+        result
+    });
 
-    println!(
-        "\nMindestens erreichbarer Wert: {}. \nAktueller Wert: {}\nBewegt {} von {}{} nach {}{}",
-        best_value,
-        evaluate_board(best_board.1, best_board.2),
-        io::get_str(best_move.0 .0),
-        ('A' as u8 + best_move.0 .1 - 1) as char,
-        best_move.0 .2,
-        ('A' as u8 + best_move.1 .0 as u8 - 1) as char,
-        best_move.1 .1
-    );
-
-    if best_move.0 .0 == 255
-        || engine::is_checkmate(best_board.0, best_board.1, best_board.2, !white)
-    {
-        engine::GameState::CheckMate(best_board)
-    } else {
-        engine::GameState::Normal(best_board)
+    result.or_else(|| get_static_best_move(board, black_figures, white_figures, white))
+}
+
+/// Returns the principal variation: the moves both players are expected to make, starting with `first_move`
+/// and followed by the best moves stored in `table`, at most `length` moves.
+/// It ends early at a board without a stored move, with an illegal stored move (of another board with the same index) or at a repeated board.
+pub fn get_principal_variation(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+    first_move: engine::Move,
+    table: &TranspositionTable,
+    length: u8,
+) -> Vec<engine::Move> {
+    let (mut board, mut black_figures, mut white_figures, mut white) =
+        (board, black_figures, white_figures, white);
+    let mut moves = vec![first_move];
+    let mut hashes = vec![board.hash];
+
+    loop {
+        let r#move = *moves.last().unwrap();
+        (board, black_figures, white_figures) =
+            engine::move_figure(board, black_figures, white_figures, r#move, white);
+        white = !white;
+
+        if moves.len() >= length as usize || hashes.contains(&board.hash) {
+            return moves;
+        }
+        hashes.push(board.hash);
+
+        let next_move = match table.probe(board.hash) {
+            Some(entry) => entry.best_move,
+            None => return moves,
+        };
+        if !engine::generate_legal_moves(board, black_figures, white_figures, white)
+            .contains(&next_move)
+        {
+            return moves;
+        }
+        moves.push(next_move);
     }
 }
 
-fn print_progress(progress: u8, white: bool) {
-    if white {
-        print!("\rWeiß> [");
-    } else {
-        print!("\rSchwarz> [");
+/// Returns the move after which the evaluation of the board is best for the player, without searching any further moves,
+/// and the evaluation. It is used if the search was stopped before the first depth was finished.
+///
+/// Returns [`None`] if the player has no move.
+fn get_static_best_move(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+) -> Option<(i32, engine::Move)> {
+    engine::generate_legal_moves(board, black_figures, white_figures, white)
+        .iter()
+        .map(|&r#move| {
+            let (_, black_figures, white_figures) =
+                engine::move_figure(board, black_figures, white_figures, r#move, white);
+            (evaluate_board(black_figures, white_figures), r#move)
+        })
+        .max_by_key(|&(value, _)| if white { value } else { -value })
+}
+
+/// Searches the best move of the player with a depth of `depth` plies.
+///
+/// `history` contains all boards of the game, the last one being the current board.
+/// `table` is used by [`min_max`] for all boards after the first move.
+/// The best move stored for the board in `table` (e.g. by the search with one ply less) is searched first, and the best move found is stored.
+/// `heuristics` orders the moves of [`min_max`] and can be reused for the next search of the same board.
+/// `config` switches the enhancements of [`min_max`] on or off.
+/// `progress` is called with the number of figures (out of 16) that were searched so far.
+///
+/// Returns the value of the board after the best move and the best move,
+/// or [`None`] if the player has no move or the search was stopped with [`SearchConfig::stop`].
+#[allow(clippy::too_many_arguments)]
+pub fn search(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+    depth: u8,
+    history: &[engine::Board],
+    table: &TranspositionTable,
+    heuristics: &mut Heuristics,
+    config: &SearchConfig,
+    progress: &mut dyn FnMut(u8),
+) -> Option<(i32, engine::Move)> {
+    let mut history = history.to_vec();
+    let (mut board, mut black_figures, mut white_figures) = (board, black_figures, white_figures);
+    let own_figures = if white { white_figures } else { black_figures };
+
+    let mut best_value = if white { i32::MIN } else { i32::MAX };
+    let mut best_move = None;
+
+    let mut alpha = i32::MIN;
+    let mut beta = i32::MAX;
+
+    let mut moves = engine::generate_legal_moves(board, black_figures, white_figures, white);
+
+    // The best move of the last search is probably still good, so the other moves can be cut off sooner.
+    if let Some(entry) = table.probe(board.hash) {
+        if let Some(index) = moves.iter().position(|&r#move| r#move == entry.best_move) {
+            moves[..=index].rotate_right(1);
+        }
     }
 
-    for i in 0..16 {
-        if i < progress {
-            print!("#");
-        } else {
-            print!(" ");
+    for &r#move in moves.iter() {
+        // Apart from the first move, the moves are generated figure by figure, so all figures in front of the moving one were searched.
+        let (from_x, from_y) = engine::bitboard::to_position(r#move.from);
+        let searched = own_figures
+            .iter()
+            .position(|figure| figure.0 != 255 && figure.1 == from_x && figure.2 == from_y)
+            .unwrap_or(0);
+        progress(searched as u8);
+
+        let undo = engine::make_move(
+            &mut board,
+            &mut black_figures,
+            &mut white_figures,
+            r#move,
+            white,
+        );
+
+        let value = min_max(
+            &mut board,
+            &mut black_figures,
+            &mut white_figures,
+            !white,
+            alpha,
+            beta,
+            depth.max(1) - 1,
+            1,
+            &mut history,
+            table,
+            heuristics,
+            config,
+        );
+
+        engine::unmake_move(
+            &mut board,
+            &mut black_figures,
+            &mut white_figures,
+            r#move,
+            undo,
+            white,
+        );
+
+        if config.is_stopped() {
+            return None;
+        }
+
+        if white && value > alpha {
+            alpha = value;
+            best_value = value;
+            best_move = Some(r#move);
+        } else if !white && value < beta {
+            beta = value;
+            best_value = value;
+            best_move = Some(r#move);
         }
+
+        if beta <= alpha {
+            break;
+        }
+    }
+
+    progress(16);
+
+    if let Some(best_move) = best_move {
+        table.store(
+            board.hash,
+            transposition::Entry {
+                depth,
+                value: best_value,
+                bound: Bound::Exact,
+                best_move,
+            },
+        );
     }
 
-    print!("]");
+    // Every move is checkmate for the player, the first one is as good as any other.
+    best_move
+        .or_else(|| moves.first().copied())
+        .map(|best_move| (best_value, best_move))
+}
+
+/// Configures the search: the number of threads, the enhancements of [`min_max`],
+/// which can be switched off e.g. to measure how much stronger each one makes the AI, and the endgame tables.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchConfig<'a> {
+    /// The number of threads [`iterative_deepening`] searches with (see https://www.chessprogramming.org/Lazy_SMP).
+    /// With one thread, the search always finds the same move for the same depth.
+    pub threads: usize,
+    /// Searches all moves after the first one with a zero window first, to prove that they are not better
+    /// (see https://www.chessprogramming.org/Principal_Variation_Search).
+    pub principal_variation_search: bool,
+    /// Cuts off a board if the player is still too good after passing the move to the opponent
+    /// (see https://www.chessprogramming.org/Null_Move_Pruning).
+    pub null_move_pruning: bool,
+    /// Searches late quiet moves with less depth (see https://www.chessprogramming.org/Late_Move_Reductions).
+    pub late_move_reductions: bool,
+    /// Searches boards where the player is in check one ply deeper (see https://www.chessprogramming.org/Check_Extensions).
+    pub check_extensions: bool,
+    /// The endgame tables [`min_max`] looks up boards with few figures in, see [`tablebase`].
+    pub tablebases: Option<&'a tablebase::Tablebases>,
+    /// Set to `true` from another thread to stop the search as fast as possible. The results of a stopped search must be ignored.
+    pub stop: Option<&'a AtomicBool>,
+}
+
+impl Default for SearchConfig<'_> {
+    fn default() -> Self {
+        SearchConfig {
+            threads: 1,
+            principal_variation_search: true,
+            null_move_pruning: true,
+            late_move_reductions: true,
+            check_extensions: true,
+            tablebases: None,
+            stop: None,
+        }
+    }
+}
 
-    std::io::stdout().flush().expect("Could not flush stdout!");
+impl SearchConfig<'_> {
+    /// Checks if the search was stopped with [`SearchConfig::stop`].
+    fn is_stopped(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
 }
 
+/// Searches the value of the board with the min-max algorithm and alpha-beta pruning.
+/// White tries to maximize the value, black tries to minimize it.
+///
+/// `ply` is the number of moves made since the board the search started at.
+/// A player who is checkmate `ply` plies after that board gets the value `i32::MIN + 100 + ply` (white)
+/// or `i32::MAX - 100 - ply` (black), so a faster checkmate has a higher value for the other player.
+///
+/// `history` contains all previous boards of the game and the search, the last one being the board before `board`.
+/// The moves are made with [`engine::make_move`] and taken back with [`engine::unmake_move`],
+/// so `board` and the positions are the same as before when the function returns.
+///
+/// Boards that were already searched deep enough are not searched again, their value is read from `table`.
+/// At the end of the search (`depth == 0`), the board is evaluated with [`quiescence`].
+/// Boards with few figures are looked up in the endgame tables of `config` instead of being searched.
+///
+/// The moves are searched in the order of [`Heuristics::order`], and moves that cut off the others are stored in `heuristics`.
+/// `config` switches the enhancements of the search on or off.
+#[allow(clippy::too_many_arguments)]
 pub fn min_max(
-    board: engine::Board,
-    black_figures: engine::Positions,
-    white_figures: engine::Positions,
+    board: &mut engine::Board,
+    black_figures: &mut engine::Positions,
+    white_figures: &mut engine::Positions,
     white: bool,
     mut alpha: i32,
     mut beta: i32,
-    depth: u8,
+    mut depth: u8,
+    ply: u8,
+    history: &mut Vec<engine::Board>,
+    table: &TranspositionTable,
+    heuristics: &mut Heuristics,
+    config: &SearchConfig,
 ) -> i32 {
-    if black_figures[0].0 == 255 {
-        return i32::MAX - 100 + depth as i32;
-    } else if white_figures[0].0 == 255 {
-        return i32::MIN + 100 - depth as i32;
+    if config.is_stopped() {
+        return 0;
+    }
+    NODES.fetch_add(1, Ordering::Relaxed);
+
+    // Draws by the fifty-move rule, insufficient material or repetition are scored as 0.
+    // In the search, the first repetition already counts as a draw, because the player could repeat the board again.
+    if board.halfmove_clock >= 100
+        || engine::is_insufficient_material(*black_figures, *white_figures)
+        || engine::count_repetitions(*board, history) >= 1
+    {
+        return 0;
+    }
+
+    // A check must be answered, so the search must not end before the answer (the quiescence search does not search checks).
+    let in_check = !engine::is_board_valid(*board, *black_figures, *white_figures, white);
+    if config.check_extensions && in_check && ply < MAX_DEPTH {
+        depth += 1;
     }
 
     if depth == 0 {
-        if {
-            let mut could_catch_king = false;
+        return quiescence(
+            board,
+            black_figures,
+            white_figures,
+            white,
+            alpha,
+            beta,
+            config,
+        );
+    }
 
-            let king = if white {
-                black_figures[0]
+    // The tables know the result of the board if both players play perfectly.
+    // They count the fifty-move rule from the last catch or pawn move, so they are only looked up right after one.
+    if board.halfmove_clock == 0 {
+        if let Some(wdl) = config.tablebases.and_then(|tablebases| {
+            tablebases.probe_wdl(*board, *black_figures, *white_figures, white)
+        }) {
+            let value = wdl.get_value(ply);
+            return if white { value } else { -value };
+        }
+    }
+
+    let mut hash_move = engine::moves::NULL_MOVE;
+    if let Some(entry) = table.probe(board.hash) {
+        hash_move = entry.best_move;
+        if entry.depth >= depth {
+            let value = transposition::from_table_value(entry.value, ply);
+            match entry.bound {
+                Bound::Exact => return value,
+                Bound::Lower => alpha = alpha.max(value),
+                Bound::Upper => beta = beta.min(value),
+            }
+            if beta <= alpha {
+                return value;
+            }
+        }
+    }
+
+    // If the player is still too good after passing the move, any real move will be even better, so the board is cut off.
+    // This is wrong if passing would be the best move (zugzwang), which mostly happens in endgames with only pawns left,
+    // and if the last move was a null move too, which would only search the same board with less depth.
+    if config.null_move_pruning
+        && !in_check
+        && depth >= 3
+        && has_pieces(if white { white_figures } else { black_figures })
+        && history
+            .last()
+            .is_none_or(|previous| previous.squares != board.squares)
+    {
+        let value = evaluate_board(*black_figures, *white_figures);
+        if (white && value >= beta) || (!white && value <= alpha) {
+            let reduction = if depth > 6 { 3 } else { 2 };
+
+            history.push(*board);
+            let undo = engine::make_null_move(board);
+            let (null_alpha, null_beta) = if white {
+                (beta - 1, beta)
             } else {
-                white_figures[0]
+                (alpha, alpha + 1)
             };
+            let var = min_max(
+                board,
+                black_figures,
+                white_figures,
+                !white,
+                null_alpha,
+                null_beta,
+                depth - 1 - reduction,
+                ply + 1,
+                history,
+                table,
+                heuristics,
+                config,
+            );
+            engine::unmake_null_move(board, undo);
+            history.pop();
 
-            'search: for figure in {
-                if white {
-                    white_figures
-                } else {
-                    black_figures
-                }
-            } {
-                if figure.0 == 255 {
-                    continue;
-                }
+            if config.is_stopped() {
+                return 0;
+            }
+            if white && var >= beta {
+                return beta;
+            }
+            if !white && var <= alpha {
+                return alpha;
+            }
+        }
+    }
 
-                let moves = engine::get_valid_moves(board, figure.0, figure.1, figure.2);
+    let mut moves = engine::generate_legal_moves(*board, *black_figures, *white_figures, white);
 
-                for subset in moves {
-                    for r#move in subset {
-                        if r#move.0 > 8 {
-                            break;
-                        }
+    // Without a move, the player is checkmate, or stalemate if the king is not in check right now.
+    if moves.is_empty() {
+        return if !in_check {
+            0
+        } else if white {
+            i32::MIN + 100 + ply as i32
+        } else {
+            i32::MAX - 100 - ply as i32
+        };
+    }
 
-                        if r#move.0 as u8 == king.1 && r#move.1 as u8 == king.2 {
-                            could_catch_king = true;
-                            break 'search;
-                        }
-                    }
-                }
-            }
+    heuristics.order(&mut moves, board, hash_move, ply, white);
 
-            could_catch_king
-        } {
-            return if white {
-                i32::MAX - 100 + depth as i32
+    let (original_alpha, original_beta) = (alpha, beta);
+    let mut best_value = if white { i32::MIN } else { i32::MAX };
+    let mut best_move = engine::moves::NULL_MOVE;
+
+    history.push(*board);
+
+    for (index, &r#move) in moves.iter().enumerate() {
+        let undo = engine::make_move(board, black_figures, white_figures, r#move, white);
+
+        // Late quiet moves are probably bad, because the moves are ordered, so they are searched with less depth.
+        let reduction = if config.late_move_reductions
+            && index >= 3
+            && depth >= 3
+            && !in_check
+            && ordering::is_quiet(r#move)
+            && engine::is_board_valid(*board, *black_figures, *white_figures, !white)
+        {
+            if index >= 6 && depth >= 6 {
+                2
             } else {
-                i32::MIN + 100 - depth as i32
-            };
+                1
+            }
+        } else {
+            0
+        };
+
+        // All moves after the first one are searched with a zero window,
+        // which only proves that the move is not better than the best move so far, but is much faster.
+        let (zero_alpha, zero_beta) = if !config.principal_variation_search || index == 0 {
+            (alpha, beta)
+        } else if white {
+            (alpha, alpha + 1)
+        } else {
+            (beta - 1, beta)
+        };
+
+        let mut var = min_max(
+            board,
+            black_figures,
+            white_figures,
+            !white,
+            zero_alpha,
+            zero_beta,
+            depth - 1 - reduction,
+            ply + 1,
+            history,
+            table,
+            heuristics,
+            config,
+        );
+
+        // A reduced move that is better than expected is searched again with the full depth.
+        if reduction > 0 && ((white && var > alpha) || (!white && var < beta)) {
+            var = min_max(
+                board,
+                black_figures,
+                white_figures,
+                !white,
+                zero_alpha,
+                zero_beta,
+                depth - 1,
+                ply + 1,
+                history,
+                table,
+                heuristics,
+                config,
+            );
         }
 
-        return evaluate_board(black_figures, white_figures);
-    }
+        // A move that is better in the zero window is searched again with the full window to get its real value.
+        if (zero_alpha, zero_beta) != (alpha, beta) && var > alpha && var < beta {
+            var = min_max(
+                board,
+                black_figures,
+                white_figures,
+                !white,
+                alpha,
+                beta,
+                depth - 1,
+                ply + 1,
+                history,
+                table,
+                heuristics,
+                config,
+            );
+        }
 
-    let mut min = i32::MAX - 100 + depth as i32;
-    let mut max = i32::MIN + 100 - depth as i32;
+        engine::unmake_move(board, black_figures, white_figures, r#move, undo, white);
+
+        if (white && var > best_value) || (!white && var < best_value) {
+            best_value = var;
+            best_move = r#move;
+        }
 
-    'outer: for figure in {
         if white {
-            white_figures
+            alpha = alpha.max(var);
         } else {
-            black_figures
+            beta = beta.min(var);
         }
-    } {
-        if figure.0 == 255 {
-            continue;
+
+        if beta <= alpha {
+            heuristics.store_cutoff(r#move, ply, depth, white);
+            break;
         }
 
-        let moves = engine::get_valid_moves(board, figure.0, figure.1, figure.2);
+        // There is no better move than checkmate in the next move.
+        if (white && var == i32::MAX - 100 - (ply as i32 + 1))
+            || (!white && var == i32::MIN + 100 + (ply as i32 + 1))
+        {
+            break;
+        }
+    }
 
-        for subset in moves {
-            for r#move in subset {
-                if r#move.0 > 8 {
-                    break;
-                }
+    history.pop();
 
-                let (board, black_figures, white_figures) = engine::move_figure(
-                    board,
-                    black_figures,
-                    white_figures,
-                    figure.0,
-                    figure.1,
-                    figure.2,
-                    r#move.0 as u8,
-                    r#move.1 as u8,
-                    white,
-                );
-
-                let var = min_max(
-                    board,
-                    black_figures,
-                    white_figures,
-                    !white,
-                    alpha,
-                    beta,
-                    depth - 1,
-                );
-
-                if white && var == i32::MAX - 100 + depth as i32 - 1 {
-                    return var;
-                } else if !white && var == i32::MIN + 100 - depth as i32 - 1 {
-                    return var;
-                }
+    // The values of a stopped search are wrong and must not be stored.
+    if !config.is_stopped() {
+        let bound = if best_value <= original_alpha {
+            Bound::Upper
+        } else if best_value >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        table.store(
+            board.hash,
+            transposition::Entry {
+                depth,
+                value: transposition::to_table_value(best_value, ply),
+                bound,
+                best_move,
+            },
+        );
+    }
 
-                if var < min {
-                    if !white {
-                        beta = var;
-                    }
-                    min = var;
-                }
-                if var > max {
-                    if white {
-                        alpha = var;
-                    }
-                    max = var;
-                }
+    best_value
+}
 
-                if beta <= alpha {
-                    break 'outer;
-                }
-            }
+/// Checks if the player has any figure besides the king and the pawns.
+fn has_pieces(figures: &engine::Positions) -> bool {
+    figures.iter().any(|figure| {
+        figure.0 != 255
+            && !matches!(
+                figures::colored_figure_to_blank_figure(figure.0),
+                figures::KING | figures::PAWN
+            )
+    })
+}
+
+/// How much a catch may improve the value more than the value of the caught figure (for [`quiescence`]).
+const DELTA_MARGIN: i32 = 200;
+
+/// Searches all catches and promotions until no more are possible, before the board is evaluated
+/// (see https://www.chessprogramming.org/Quiescence_Search).
+/// This way, the search does not end in the middle of an exchange, e.g. after a queen caught a defended pawn.
+///
+/// The player can always stop catching instead (stand pat), so the value is at least the value of the board.
+/// Catches that would not reach `alpha` (white) or `beta` (black) even with [`DELTA_MARGIN`] more are not searched (delta pruning).
+/// Checks are not searched, so a checkmate is only found by [`min_max`].
+pub fn quiescence(
+    board: &mut engine::Board,
+    black_figures: &mut engine::Positions,
+    white_figures: &mut engine::Positions,
+    white: bool,
+    mut alpha: i32,
+    mut beta: i32,
+    config: &SearchConfig,
+) -> i32 {
+    if config.is_stopped() {
+        return 0;
+    }
+    NODES.fetch_add(1, Ordering::Relaxed);
+
+    if engine::is_insufficient_material(*black_figures, *white_figures) {
+        return 0;
+    }
+
+    let stand_pat = evaluate_board(*black_figures, *white_figures);
+    if white {
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+    } else {
+        if stand_pat <= alpha {
+            return stand_pat;
+        }
+        beta = beta.min(stand_pat);
+    }
+
+    let mut best_value = stand_pat;
+
+    let mut captures =
+        engine::generate_legal_captures(*board, *black_figures, *white_figures, white);
+
+    // The most valuable figures are caught first, with the least valuable figures (MVV-LVA),
+    // so that the best catches are found early and the others are cut off.
+    captures.sort_by_cached_key(|&r#move| std::cmp::Reverse(ordering::get_mvv_lva(board, r#move)));
+
+    for &r#move in captures.iter() {
+        let mut gain = get_caught_value(board, r#move) + DELTA_MARGIN;
+        if r#move.promotion != 0 {
+            gain += evaluation::MG_VALUES[r#move.promotion as usize]
+                - evaluation::MG_VALUES[figures::PAWN as usize];
+        }
+
+        if (white && stand_pat + gain <= alpha) || (!white && stand_pat - gain >= beta) {
+            continue;
+        }
+
+        let undo = engine::make_move(board, black_figures, white_figures, r#move, white);
+        let var = quiescence(
+            board,
+            black_figures,
+            white_figures,
+            !white,
+            alpha,
+            beta,
+            config,
+        );
+        engine::unmake_move(board, black_figures, white_figures, r#move, undo, white);
+
+        if white {
+            best_value = best_value.max(var);
+            alpha = alpha.max(var);
+        } else {
+            best_value = best_value.min(var);
+            beta = beta.min(var);
+        }
+
+        if beta <= alpha {
+            break;
         }
     }
 
-    return if white { max } else { min };
+    best_value
+}
+
+/// Returns the middle game value of the figure the move catches, or `0` if it catches none.
+fn get_caught_value(board: &engine::Board, r#move: engine::Move) -> i32 {
+    let caught = if r#move.flags & engine::moves::EN_PASSANT != 0 {
+        figures::PAWN
+    } else {
+        figures::colored_figure_to_blank_figure(board.squares[r#move.to as usize])
+    };
+    evaluation::MG_VALUES[caught as usize]
 }
 
-// Evaluation function inspired by PeSTO: https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function
+/// Evaluates the board from the view of white, without searching any moves.
+///
+/// Evaluation function inspired by PeSTO: https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function
 pub fn evaluate_board(black_figures: engine::Positions, white_figures: engine::Positions) -> i32 {
-    let mut mg_score = 0;
-    let mut eg_score = 0;
+    let [white, black] = evaluation::get_terms(&black_figures, &white_figures);
 
-    let mut game_phase = 0;
+    evaluation::blend(
+        white.get_total() - black.get_total(),
+        evaluation::get_game_phase(&black_figures, &white_figures),
+    )
+}
 
-    for i in 0..black_figures.len() {
-        let white = white_figures[i];
-        let black = black_figures[i];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::fen;
 
-        if white.0 != 255 {
-            mg_score += evaluation::MG_VALUES[white.0 as usize]
-                + evaluation::MG_TABLES[white.0 as usize]
-                    [(8 * (white.2 - 1) + (white.1 - 1)) as usize];
-            eg_score += evaluation::EG_VALUES[white.0 as usize]
-                + evaluation::EG_TABLES[white.0 as usize]
-                    [(8 * (white.2 - 1) + (white.1 - 1)) as usize];
+    fn turn_of(fen: &str) -> (engine::GameState, engine::Move, i32, MoveSource) {
+        let game = fen::from_fen(fen).unwrap();
+        turn(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            Duration::from_millis(100),
+            &game.history,
+            &TranspositionTable::new(1),
+            &SearchConfig::default(),
+            None,
+            &mut |_| {},
+            &mut |_, _, _| {},
+        )
+    }
 
-            game_phase += evaluation::GAME_PHASE_INC[white.0 as usize]
-        }
+    #[test]
+    fn stopped_search_falls_back_to_the_evaluation() {
+        let game = fen::from_fen("k7/8/8/3q4/8/8/3R4/K7 w - - 0 1").unwrap();
+        let (_, r#move) =
+            get_static_best_move(game.board, game.black_figures, game.white_figures, true).unwrap();
+        assert_eq!(engine::bitboard::to_position(r#move.from), (4, 2));
+        assert_eq!(engine::bitboard::to_position(r#move.to), (4, 5));
+    }
 
-        if black.0 != 255 {
-            mg_score -= evaluation::MG_VALUES[(black.0 - 10) as usize]
-                + evaluation::MG_TABLES[(black.0 - 10) as usize]
-                    [(8 * (7 - (black.2 - 1)) + (black.1 - 1)) as usize];
-            eg_score -= evaluation::EG_VALUES[(black.0 - 10) as usize]
-                + evaluation::EG_TABLES[(black.0 - 10) as usize]
-                    [(8 * (7 - (black.2 - 1)) + (black.1 - 1)) as usize];
+    #[test]
+    fn searches_with_several_threads_do_not_stop_later_searches() {
+        let game = fen::from_fen(fen::INITIAL_FEN).unwrap();
+        let table = TranspositionTable::new(1);
+        let stop = AtomicBool::new(false);
+        let config = SearchConfig {
+            threads: 2,
+            stop: Some(&stop),
+            ..SearchConfig::default()
+        };
+        iterative_deepening(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            2,
+            None,
+            &game.history,
+            &table,
+            &config,
+            &mut |_| {},
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        assert!(!stop.load(Ordering::Relaxed));
 
-            game_phase += evaluation::GAME_PHASE_INC[(black.0 - 10) as usize]
-        }
+        assert!(search(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            2,
+            &game.history,
+            &table,
+            &mut Heuristics::new(),
+            &SearchConfig::default(),
+            &mut |_| {},
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn principal_variation_follows_the_table() {
+        let game = fen::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+        let table = TranspositionTable::new(1);
+        let (_, best_move) = iterative_deepening(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            3,
+            None,
+            &game.history,
+            &table,
+            &SearchConfig::default(),
+            &mut |_| {},
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+
+        // Rh8 is checkmate, after it there is no move.
+        let moves = get_principal_variation(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            best_move,
+            &table,
+            3,
+        );
+        assert_eq!(moves, vec![best_move]);
+
+        let game = fen::from_fen(fen::INITIAL_FEN).unwrap();
+        let (_, best_move) = iterative_deepening(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            4,
+            None,
+            &game.history,
+            &table,
+            &SearchConfig::default(),
+            &mut |_| {},
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        let moves = get_principal_variation(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            best_move,
+            &table,
+            4,
+        );
+        assert_eq!(moves.len(), 4);
+        assert_eq!(moves[0], best_move);
     }
 
-    let mg_phase = if game_phase > 24 { 24 } else { game_phase };
-    let eg_phase = 24 - mg_phase;
+    #[test]
+    fn player_without_moves_is_checkmate_or_stalemate() {
+        let (state, r#move, value, _) = turn_of("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        assert!(matches!(
+            state,
+            engine::GameState::Draw(_, engine::DrawReason::Stalemate)
+        ));
+        assert_eq!(r#move, engine::moves::NULL_MOVE);
+        assert_eq!(value, 0);
 
-    (mg_score * mg_phase + eg_score * eg_phase) / 24
+        let (state, r#move, value, _) = turn_of("k1Q5/8/1K6/8/8/8/8/8 b - - 0 1");
+        assert!(matches!(state, engine::GameState::CheckMate(_)));
+        assert_eq!(r#move, engine::moves::NULL_MOVE);
+        assert_eq!(value, i32::MAX);
+    }
 }
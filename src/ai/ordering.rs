@@ -0,0 +1,115 @@
+//! The order in which [`super::min_max`] searches the moves (see https://www.chessprogramming.org/Move_Ordering).
+//!
+//! The earlier a good move is searched, the more of the other moves are cut off by alpha-beta pruning. The moves are searched in this order:
+//! 1. The best move from the transposition table (the hash move).
+//! 2. Catches and promotions, the most valuable caught figure with the least valuable figure first (MVV-LVA).
+//! 3. The killer moves: quiet moves that caused a cutoff at the same ply before.
+//! 4. All other moves, by how often they caused a cutoff before (history heuristic).
+
+use super::MAX_DEPTH;
+use crate::engine::{self, figures};
+
+/// The rank of the blank figures for MVV-LVA, indexed by [`figures::Figure`]. The king is the most valuable attacker.
+const RANKS: [i32; 10] = [0, 6, 5, 2, 0, 3, 0, 4, 0, 1];
+
+const HASH_MOVE_SCORE: i32 = 3_000_000;
+const CAPTURE_SCORE: i32 = 2_000_000;
+const KILLER_SCORE: i32 = 1_000_000;
+
+/// What the search learned about quiet moves so far. It is kept for all depths of one search.
+pub struct Heuristics {
+    /// Two quiet moves for every ply that caused a cutoff, the latest one first.
+    killers: [[engine::Move; 2]; MAX_DEPTH as usize],
+    /// How good the quiet moves of white (index 0) and black (index 1) were, indexed by the start and target position.
+    history: [[[i32; 64]; 64]; 2],
+}
+
+impl Heuristics {
+    pub fn new() -> Heuristics {
+        Heuristics {
+            killers: [[engine::moves::NULL_MOVE; 2]; MAX_DEPTH as usize],
+            history: [[[0; 64]; 64]; 2],
+        }
+    }
+
+    /// Sorts the moves of the player (`white`) at the ply `ply` in the order they should be searched in.
+    ///
+    /// `hash_move` is the best move from the transposition table, or [`engine::moves::NULL_MOVE`] if there is none.
+    pub fn order(
+        &self,
+        moves: &mut [engine::Move],
+        board: &engine::Board,
+        hash_move: engine::Move,
+        ply: u8,
+        white: bool,
+    ) {
+        let killers = self.killers[ply as usize % MAX_DEPTH as usize];
+        let history = &self.history[!white as usize];
+
+        moves.sort_by_cached_key(|&r#move| {
+            let score = if r#move == hash_move {
+                HASH_MOVE_SCORE
+            } else if !is_quiet(r#move) {
+                CAPTURE_SCORE + get_mvv_lva(board, r#move)
+            } else if r#move == killers[0] {
+                KILLER_SCORE + 1
+            } else if r#move == killers[1] {
+                KILLER_SCORE
+            } else {
+                history[r#move.from as usize][r#move.to as usize]
+            };
+            std::cmp::Reverse(score)
+        });
+    }
+
+    /// Remembers that the move of the player (`white`) caused a cutoff at the ply `ply` with `depth` plies left.
+    /// Only quiet moves are remembered, catches are ordered by MVV-LVA anyway.
+    pub fn store_cutoff(&mut self, r#move: engine::Move, ply: u8, depth: u8, white: bool) {
+        if !is_quiet(r#move) {
+            return;
+        }
+
+        let killers = &mut self.killers[ply as usize % MAX_DEPTH as usize];
+        if killers[0] != r#move {
+            killers[1] = killers[0];
+            killers[0] = r#move;
+        }
+
+        // A cutoff close to the root cuts off more boards, so it counts more.
+        let history = &mut self.history[!white as usize];
+        let score = &mut history[r#move.from as usize][r#move.to as usize];
+        *score += depth as i32 * depth as i32;
+
+        // The scores must stay below the scores of the killer moves.
+        if *score >= KILLER_SCORE {
+            for scores in history.iter_mut() {
+                for score in scores.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+    }
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Heuristics::new()
+    }
+}
+
+/// Checks if the move neither catches a figure nor promotes a pawn.
+pub fn is_quiet(r#move: engine::Move) -> bool {
+    r#move.flags & engine::moves::CAPTURE == 0 && r#move.promotion == 0
+}
+
+/// Returns the MVV-LVA score of a catch or promotion: the more valuable the caught figure and the less valuable the moving figure, the higher.
+pub fn get_mvv_lva(board: &engine::Board, r#move: engine::Move) -> i32 {
+    let attacker = figures::colored_figure_to_blank_figure(board.squares[r#move.from as usize]);
+    let victim = if r#move.flags & engine::moves::EN_PASSANT != 0 {
+        figures::PAWN
+    } else {
+        figures::colored_figure_to_blank_figure(board.squares[r#move.to as usize])
+    };
+
+    10 * (RANKS[victim as usize] + RANKS[r#move.promotion as usize]) - RANKS[attacker as usize]
+}
@@ -0,0 +1,1620 @@
+//! Endgame tables in the Syzygy format (see https://www.chessprogramming.org/Syzygy_Bases).
+//!
+//! For every board with few figures, the tables store if the player to move wins, draws or loses (WDL, `.rtbw` files)
+//! and how many plies it takes until the next catch or pawn move (distance to zeroing, DTZ, `.rtbz` files).
+//! A table is named after its figures, e.g. `KQvKR`. The stronger player is always stored as white.
+//!
+//! A board is turned into an index into the table, which does not count boards that are mirrors of each other.
+//! The values are compressed with recursive pairing: a Huffman coded symbol stands for a sequence of values.
+//! The format is only documented by its reference implementation, which is followed closely here.
+//! The tests only read tables written by our own generator (see `testdata/syzygy`), reading the official files has not been verified yet.
+//! All tables are read into memory and checked when the directory is opened, so that a broken file is an error there and not in the search.
+
+use crate::engine::{self, figures};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The value of a board that is won according to the tables, for the player to move.
+/// It is lower than the value of any checkmate the search found and higher than the value of any evaluation.
+pub const WIN_VALUE: i32 = 1_000_000;
+
+/// The most figures a table can contain.
+const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// The flags of a part of a table.
+/// The part of a DTZ table stores the boards with black to move.
+const BLACK_TO_MOVE: u8 = 1;
+/// The values of a DTZ table are mapped to the real distances with a map for every result.
+const MAPPED: u8 = 2;
+/// A DTZ table stores won boards in plies instead of moves.
+const WIN_PLIES: u8 = 4;
+/// A DTZ table stores lost boards in plies instead of moves.
+const LOSS_PLIES: u8 = 8;
+/// The maps of a DTZ table store 2 byte values.
+const WIDE: u8 = 16;
+/// All boards of the part have the same value, nothing is compressed.
+const SINGLE_VALUE: u8 = 128;
+
+/// The result of a board for the player to move.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Wdl {
+    Loss,
+    /// The player would lose, but the opponent can not win before the fifty-move rule ends the game.
+    BlessedLoss,
+    Draw,
+    /// The player would win, but not before the fifty-move rule ends the game.
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    /// Converts the value stored in a WDL table, from `-2` (loss) to `2` (win).
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            1 => Wdl::CursedWin,
+            2 => Wdl::Win,
+            _ => Wdl::Draw,
+        }
+    }
+
+    /// Returns the value of the result for the player to move, `ply` plies after the board the search started at.
+    /// A win that is reached earlier has a higher value. Cursed wins and blessed losses are draws, but slightly better or worse than one.
+    pub fn get_value(self, ply: u8) -> i32 {
+        match self {
+            Wdl::Loss => -WIN_VALUE + ply as i32,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 1,
+            Wdl::Win => WIN_VALUE - ply as i32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Wdl,
+    Dtz,
+}
+
+/// Why a table returned no value.
+enum Failure {
+    /// The table of the figures is not in the directory.
+    Missing,
+    /// The DTZ table only stores the boards of the other player.
+    OtherPlayer,
+    /// The file of the table does not store a valid value for the board.
+    Broken,
+}
+
+/// The endgame tables of a directory.
+pub struct Tablebases {
+    /// The tables by their name, e.g. `KQvKR`.
+    entries: HashMap<String, Entry>,
+    max_pieces: usize,
+}
+
+impl Tablebases {
+    /// Reads all tables in the directory `directory`. Returns an error if a table is broken.
+    pub fn open(directory: &str) -> Result<Tablebases, String> {
+        let mut entries = HashMap::new();
+        let mut max_pieces = 0;
+
+        for file in std::fs::read_dir(directory).map_err(|error| error.to_string())? {
+            let path = file.map_err(|error| error.to_string())?.path();
+            if path.extension().is_none_or(|extension| extension != "rtbw") {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|name| name.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if let Some(material) = Material::parse(name) {
+                max_pieces = max_pieces.max(material.piece_count);
+                entries.insert(name.to_string(), Entry::read(&path, material)?);
+            }
+        }
+
+        if entries.is_empty() {
+            return Err("The directory contains no Syzygy tables".to_string());
+        }
+
+        Ok(Tablebases {
+            entries,
+            max_pieces,
+        })
+    }
+
+    /// Returns the number of tables.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if there are no tables, which never happens for tables returned by [`Tablebases::open`].
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the most figures a board can have to be in the tables.
+    pub fn get_max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Checks if the board could be in the tables: it has few enough figures and no player can castle anymore.
+    pub fn contains(&self, board: &engine::Board) -> bool {
+        board.castling == [0, 0]
+            && board.squares.iter().filter(|&&figure| figure != 0).count() <= self.max_pieces
+    }
+
+    /// Returns the result of the board for the player (`white`), or [`None`] if the board is not in the tables.
+    ///
+    /// The result assumes that the last catch or pawn move was just made, because the tables do not know the halfmove clock.
+    pub fn probe_wdl(
+        &self,
+        board: engine::Board,
+        black_figures: engine::Positions,
+        white_figures: engine::Positions,
+        white: bool,
+    ) -> Option<Wdl> {
+        if !self.contains(&board) {
+            return None;
+        }
+
+        let (mut board, mut black_figures, mut white_figures) =
+            (board, black_figures, white_figures);
+        self.search(
+            &mut board,
+            &mut black_figures,
+            &mut white_figures,
+            white,
+            false,
+        )
+        .map(|(value, _)| Wdl::from_value(value))
+    }
+
+    /// Returns the number of plies until the next catch or pawn move if both players play perfectly,
+    /// positive if the player (`white`) wins and negative if it loses, `0` for a draw.
+    /// Cursed wins and blessed losses have a distance of more than 100 plies.
+    ///
+    /// Returns [`None`] if the board is not in the tables.
+    pub fn probe_dtz(
+        &self,
+        board: engine::Board,
+        black_figures: engine::Positions,
+        white_figures: engine::Positions,
+        white: bool,
+    ) -> Option<i32> {
+        if !self.contains(&board) {
+            return None;
+        }
+
+        let (mut board, mut black_figures, mut white_figures) =
+            (board, black_figures, white_figures);
+        self.get_dtz(&mut board, &mut black_figures, &mut white_figures, white)
+    }
+
+    /// Chooses the best move of the player (`white`) with the tables: a move that wins as fast as possible before the fifty-move rule,
+    /// otherwise a move that draws, otherwise the move that loses as slowly as possible.
+    ///
+    /// Returns the move and the result of the board for the player, or [`None`] if the board is not in the tables or the player has no move.
+    pub fn probe_root(
+        &self,
+        board: engine::Board,
+        black_figures: engine::Positions,
+        white_figures: engine::Positions,
+        white: bool,
+    ) -> Option<(engine::Move, Wdl)> {
+        if !self.contains(&board) {
+            return None;
+        }
+
+        let (mut board, mut black_figures, mut white_figures) =
+            (board, black_figures, white_figures);
+        let halfmove_clock = board.halfmove_clock as i32;
+        let moves = engine::generate_legal_moves(board, black_figures, white_figures, white);
+
+        // The best move with its rank and distance to zeroing.
+        let mut best: Option<(i32, i32, engine::Move)> = None;
+
+        for &r#move in moves.iter() {
+            let undo = engine::make_move(
+                &mut board,
+                &mut black_figures,
+                &mut white_figures,
+                r#move,
+                white,
+            );
+
+            // The distance to zeroing is counted from the board before the move.
+            let dtz = if board.halfmove_clock == 0 {
+                self.search(
+                    &mut board,
+                    &mut black_figures,
+                    &mut white_figures,
+                    !white,
+                    false,
+                )
+                .map(|(value, _)| get_dtz_before_zeroing(-value))
+            } else {
+                self.get_dtz(&mut board, &mut black_figures, &mut white_figures, !white)
+                    .map(|dtz| -dtz - dtz.signum())
+            };
+
+            // A checkmate is always the fastest win.
+            let dtz = if dtz == Some(2)
+                && !engine::is_board_valid(board, black_figures, white_figures, !white)
+                && !engine::has_valid_move(board, black_figures, white_figures, !white)
+            {
+                Some(1)
+            } else {
+                dtz
+            };
+
+            engine::unmake_move(
+                &mut board,
+                &mut black_figures,
+                &mut white_figures,
+                r#move,
+                undo,
+                white,
+            );
+            let dtz = dtz?;
+
+            // Wins before the fifty-move rule are ranked highest, losses after it are still better than real losses.
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 {
+                    1000
+                } else {
+                    1000 - (dtz + halfmove_clock)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 {
+                    -1000
+                } else {
+                    -1000 + (-dtz + halfmove_clock)
+                }
+            } else {
+                0
+            };
+
+            // Of equally ranked moves, the one that wins the fastest or loses the slowest is chosen.
+            if best.is_none_or(|(best_rank, best_dtz, _)| {
+                rank > best_rank || (rank == best_rank && dtz != 0 && dtz < best_dtz)
+            }) {
+                best = Some((rank, dtz, r#move));
+            }
+        }
+
+        best.map(|(rank, _, r#move)| {
+            let wdl = match rank {
+                1000 => Wdl::Win,
+                -1000 => Wdl::Loss,
+                0 => Wdl::Draw,
+                rank if rank > 0 => Wdl::CursedWin,
+                _ => Wdl::BlessedLoss,
+            };
+            (r#move, wdl)
+        })
+    }
+
+    /// Searches the result of the board for the player (`white`) from `-2` (loss) to `2` (win).
+    ///
+    /// The tables do not store the right value for boards where a catch is the best move, because it is cheaper to compress another one.
+    /// So all catches (and all pawn moves if `zeroing_moves` is `true`) are searched as well.
+    /// The second value is `true` if the best move is one of them, then a DTZ table does not store the right value either.
+    fn search(
+        &self,
+        board: &mut engine::Board,
+        black_figures: &mut engine::Positions,
+        white_figures: &mut engine::Positions,
+        white: bool,
+        zeroing_moves: bool,
+    ) -> Option<(i32, bool)> {
+        let moves = engine::generate_legal_moves(*board, *black_figures, *white_figures, white);
+        let mut best_value = -2;
+        let mut searched = 0;
+
+        for &r#move in moves.iter() {
+            let pawn_move =
+                figures::colored_figure_to_blank_figure(board.squares[r#move.from as usize])
+                    == figures::PAWN;
+            if r#move.flags & engine::moves::CAPTURE == 0 && !(zeroing_moves && pawn_move) {
+                continue;
+            }
+            searched += 1;
+
+            let undo = engine::make_move(board, black_figures, white_figures, r#move, white);
+            let value = self.search(board, black_figures, white_figures, !white, false);
+            engine::unmake_move(board, black_figures, white_figures, r#move, undo, white);
+
+            let value = -value?.0;
+            if value > best_value {
+                best_value = value;
+                if value == 2 {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // If all moves were searched, the value of the table is not needed, e.g. for boards where a pawn could be caught en passant, which the tables do not know.
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best_value
+        } else {
+            self.probe_table(board, white, Kind::Wdl, 0).ok()?
+        };
+
+        if best_value >= value {
+            Some((best_value, best_value > 0 || all_searched))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    /// See [`Tablebases::probe_dtz`].
+    fn get_dtz(
+        &self,
+        board: &mut engine::Board,
+        black_figures: &mut engine::Positions,
+        white_figures: &mut engine::Positions,
+        white: bool,
+    ) -> Option<i32> {
+        let (wdl, zeroing) = self.search(board, black_figures, white_figures, white, true)?;
+        if wdl == 0 {
+            return Some(0);
+        }
+        if zeroing {
+            return Some(get_dtz_before_zeroing(wdl));
+        }
+
+        match self.probe_table(board, white, Kind::Dtz, wdl) {
+            Ok(dtz) => return Some((dtz + 100 * (wdl.abs() == 1) as i32) * wdl.signum()),
+            Err(Failure::OtherPlayer) => {}
+            Err(_) => return None,
+        }
+
+        // The table only stores the boards of the other player, so the move with the shortest distance is searched.
+        let mut min_dtz = 0xFFFF;
+        let moves = engine::generate_legal_moves(*board, *black_figures, *white_figures, white);
+        for &r#move in moves.iter() {
+            let zeroing = r#move.flags & engine::moves::CAPTURE != 0
+                || figures::colored_figure_to_blank_figure(board.squares[r#move.from as usize])
+                    == figures::PAWN;
+
+            let undo = engine::make_move(board, black_figures, white_figures, r#move, white);
+            // For a zeroing move, the distance before the move is needed, but the result after it.
+            let dtz = if zeroing {
+                self.search(board, black_figures, white_figures, !white, false)
+                    .map(|(value, _)| -get_dtz_before_zeroing(value))
+            } else {
+                self.get_dtz(board, black_figures, white_figures, !white)
+                    .map(|dtz| -dtz)
+            };
+            let checkmate = dtz == Some(1)
+                && !engine::is_board_valid(*board, *black_figures, *white_figures, !white)
+                && !engine::has_valid_move(*board, *black_figures, *white_figures, !white);
+            engine::unmake_move(board, black_figures, white_figures, r#move, undo, white);
+
+            let mut dtz = dtz?;
+            if checkmate {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        // Without a move, the player is checkmate.
+        Some(if min_dtz == 0xFFFF { -1 } else { min_dtz })
+    }
+
+    /// Looks up the value of the board for the player (`white`) in the table of its figures.
+    /// For DTZ tables, `wdl` is the result of the board, which is needed to decode the value.
+    fn probe_table(
+        &self,
+        board: &engine::Board,
+        white: bool,
+        kind: Kind,
+        wdl: i32,
+    ) -> Result<i32, Failure> {
+        let (white_name, black_name) = get_names(board);
+        if white_name.len() + black_name.len() == 2 {
+            return Ok(0);
+        }
+
+        // The stronger player is white in the table, so the colors are swapped if black is the stronger player.
+        let (entry, black_stronger) =
+            match self.entries.get(&format!("{}v{}", white_name, black_name)) {
+                Some(entry) => (entry, false),
+                None => match self.entries.get(&format!("{}v{}", black_name, white_name)) {
+                    Some(entry) => (entry, true),
+                    None => return Err(Failure::Missing),
+                },
+            };
+        let table = entry.get_table(kind).ok_or(Failure::Missing)?;
+        let material = &entry.material;
+
+        // If both players have the same figures, only the boards with white to move are stored, so the colors are swapped for black.
+        let flip = black_stronger || (material.symmetric && !white);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let player = (flip ^ !white) as usize;
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces = [0; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut lead_pawn = 0;
+        let mut file = 0;
+
+        // Tables with pawns are split into 4 parts by the file of the leading pawn,
+        // which is the pawn of the first player in the table that is closest to the edge and then to the first row.
+        if material.has_pawns {
+            lead_pawn = table.parts[0][0].pieces[0] ^ flip_color;
+            for square in 0..64 {
+                if board.squares[square] != 0 && get_piece(board.squares[square]) == lead_pawn {
+                    squares[size] = square ^ flip_squares;
+                    size += 1;
+                }
+            }
+            lead_pawns = size;
+
+            let lead = (0..lead_pawns)
+                .max_by_key(|&index| ENCODING.map_pawns[squares[index]])
+                .unwrap_or(0);
+            squares.swap(0, lead);
+
+            file = squares[0] % 8;
+            if file > 3 {
+                file = 7 - file;
+            }
+        }
+
+        // DTZ tables only store the boards of one player, unless both players have the same figures.
+        let both_stored = material.symmetric && !material.has_pawns;
+        if kind == Kind::Dtz
+            && !both_stored
+            && (table.parts[0][file].flags & BLACK_TO_MOVE) as usize != player
+        {
+            return Err(Failure::OtherPlayer);
+        }
+
+        for square in 0..64 {
+            let figure = board.squares[square];
+            if figure != 0 && !(material.has_pawns && get_piece(figure) == lead_pawn) {
+                squares[size] = square ^ flip_squares;
+                pieces[size] = get_piece(figure) ^ flip_color;
+                size += 1;
+            }
+        }
+
+        let part = &table.parts[if kind == Kind::Wdl { player } else { 0 }][file];
+
+        // The figures are sorted in the order of the table, which was chosen for the best compression.
+        for i in lead_pawns..size - 1 {
+            for j in i + 1..size {
+                if part.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // The boards are mirrored so that the first figure is on the files a to d.
+        if squares[0] % 8 > 3 {
+            for square in squares[..size].iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut index;
+        if material.has_pawns {
+            index = ENCODING.lead_pawn_index[lead_pawns][squares[0]];
+
+            squares[1..lead_pawns].sort_by_key(|&square| ENCODING.map_pawns[square]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                index += ENCODING.binomial[i][ENCODING.map_pawns[square] as usize];
+            }
+        } else {
+            // Without pawns, the boards are also mirrored so that the first figure is in the triangle a1-d1-d4.
+            if squares[0] / 8 > 3 {
+                for square in squares[..size].iter_mut() {
+                    *square ^= 56;
+                }
+            }
+
+            for i in 0..part.group_length[0] {
+                let diagonal = get_diagonal(squares[i]);
+                if diagonal == 0 {
+                    continue;
+                }
+                if diagonal > 0 {
+                    for square in squares[i..size].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            index = if material.has_unique_pieces {
+                get_unique_pieces_index(&squares)
+            } else {
+                ENCODING.map_kk[ENCODING.map_a1d1d4[squares[0]] as usize][squares[1]]
+            };
+        }
+
+        // The other groups of equal figures are encoded by the combination of their squares, leaving out the squares of the groups before.
+        index *= part.group_index[0];
+        let mut group_start = part.group_length[0];
+        let mut remaining_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut group = 1;
+        while part.group_length[group] != 0 {
+            let length = part.group_length[group];
+            squares[group_start..group_start + length].sort_unstable();
+
+            let mut combination = 0;
+            for i in 0..length {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start]
+                    .iter()
+                    .filter(|&&other| square > other)
+                    .count();
+                // Pawns can not be on the first row.
+                let pawn_adjust = if remaining_pawns { 8 } else { 0 };
+                combination += ENCODING.binomial[i + 1][square - adjust - pawn_adjust];
+            }
+
+            remaining_pawns = false;
+            index += combination * part.group_index[group];
+            group_start += length;
+            group += 1;
+        }
+
+        let value = table.decompress(part, index).ok_or(Failure::Broken)?;
+
+        match kind {
+            Kind::Wdl if value <= 4 => Ok(value - 2),
+            Kind::Wdl => Err(Failure::Broken),
+            Kind::Dtz => table
+                .map_dtz(&table.parts[0][file], value, wdl)
+                .ok_or(Failure::Broken),
+        }
+    }
+}
+
+impl std::fmt::Debug for Tablebases {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter
+            .debug_struct("Tablebases")
+            .field("tables", &self.entries.len())
+            .finish()
+    }
+}
+
+/// The WDL and DTZ table of a combination of figures. The DTZ table is optional.
+struct Entry {
+    material: Material,
+    wdl: Table,
+    dtz: Option<Table>,
+}
+
+impl Entry {
+    /// Reads the WDL table at `path` and the DTZ table next to it, if there is one.
+    fn read(path: &Path, material: Material) -> Result<Entry, String> {
+        let read = |path: &Path, kind: Kind| {
+            let bytes =
+                std::fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+            Table::parse(bytes, &material, kind)
+                .ok_or_else(|| format!("{} is no valid Syzygy table", path.display()))
+        };
+
+        let wdl = read(path, Kind::Wdl)?;
+        let dtz_path = path.with_extension("rtbz");
+        let dtz = if dtz_path.exists() {
+            Some(read(&dtz_path, Kind::Dtz)?)
+        } else {
+            None
+        };
+
+        Ok(Entry { material, wdl, dtz })
+    }
+
+    /// Returns the table of the kind, or [`None`] if there is no DTZ table.
+    fn get_table(&self, kind: Kind) -> Option<&Table> {
+        match kind {
+            Kind::Wdl => Some(&self.wdl),
+            Kind::Dtz => self.dtz.as_ref(),
+        }
+    }
+}
+
+/// The figures of a table. The first player of the name is white in the table.
+struct Material {
+    piece_count: usize,
+    has_pawns: bool,
+    /// Any player has a figure besides the king only once. Then the first three figures are encoded together.
+    has_unique_pieces: bool,
+    /// The number of pawns of the leading player (the first player, or the one with fewer pawns if both have pawns) and of the other one.
+    pawn_count: [usize; 2],
+    /// Both players have the same figures.
+    symmetric: bool,
+    /// The figures in the numbering of the tables (see [`get_piece`]), sorted.
+    pieces: Vec<u8>,
+}
+
+impl Material {
+    /// Reads the figures from the name of a table, e.g. `KRPvKR`.
+    fn parse(name: &str) -> Option<Material> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 7]; 2];
+        let mut pieces = Vec::new();
+
+        for (player, side) in [white, black].into_iter().enumerate() {
+            for letter in side.chars() {
+                let piece = match letter {
+                    'P' => 1,
+                    'N' => 2,
+                    'B' => 3,
+                    'R' => 4,
+                    'Q' => 5,
+                    'K' => 6,
+                    _ => return None,
+                };
+                counts[player][piece] += 1;
+                pieces.push(piece as u8 + 8 * player as u8);
+            }
+            if counts[player][6] != 1 {
+                return None;
+            }
+        }
+
+        let piece_count = name.len() - 1;
+        if piece_count > MAX_PIECES {
+            return None;
+        }
+
+        let pawns = [counts[0][1], counts[1][1]];
+        // The player with fewer pawns leads, because this compresses better.
+        let white_leads = pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]);
+        pieces.sort_unstable();
+
+        Some(Material {
+            piece_count,
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique_pieces: counts.iter().any(|counts| counts[1..6].contains(&1)),
+            pawn_count: if white_leads {
+                pawns
+            } else {
+                [pawns[1], pawns[0]]
+            },
+            symmetric: white == black,
+            pieces,
+        })
+    }
+}
+
+/// A table read from its file.
+struct Table {
+    bytes: Vec<u8>,
+    /// The parts of the table, indexed by the player to move in the table (only WDL tables store both)
+    /// and the file of the leading pawn (only tables with pawns are split).
+    parts: [[Part; 4]; 2],
+}
+
+/// A part of a table that is compressed on its own.
+#[derive(Clone, Default)]
+struct Part {
+    flags: u8,
+    /// The figures in the order they are encoded in.
+    pieces: [u8; MAX_PIECES],
+    /// The number of figures of every group, ending with `0`. The first group are the leading pawns, or the first two or three figures.
+    group_length: [usize; MAX_PIECES + 1],
+    /// The factor of every group in the index, the one after the last group is the size of the table.
+    group_index: [u64; MAX_PIECES + 1],
+    block_size: usize,
+    /// Every `span` values, there is an entry in the sparse index.
+    span: usize,
+    block_count: usize,
+    /// The number of bits of the shortest Huffman code, or the value if all values are the same.
+    min_length: usize,
+    /// The offset of the lowest symbol for every code length.
+    lowest_symbols: usize,
+    /// The lowest code of every code length, padded to 64 bits.
+    base: Vec<u64>,
+    /// How many values (minus one) every symbol stands for.
+    symbol_lengths: Vec<u32>,
+    /// The offset of the pairs of symbols every symbol stands for (3 bytes each).
+    pairs: usize,
+    /// The offset of the sparse index: the block and the offset in it for every `span` values (6 bytes each).
+    sparse_index: usize,
+    /// The offset of the number of values (minus one) in every block (2 bytes each).
+    block_lengths: usize,
+    block_lengths_size: usize,
+    /// The offset of the first block.
+    data: usize,
+    /// The offsets of the maps of a DTZ table for a win, a loss, a cursed win and a blessed loss.
+    maps: [usize; 4],
+}
+
+impl Table {
+    /// Reads the header of the table. Returns [`None`] if the file is no table of the figures.
+    fn parse(bytes: Vec<u8>, material: &Material, kind: Kind) -> Option<Table> {
+        let magic = match kind {
+            Kind::Wdl => WDL_MAGIC,
+            Kind::Dtz => DTZ_MAGIC,
+        };
+        if bytes.len() < 5 || bytes[..4] != magic {
+            return None;
+        }
+
+        // The first byte stores if the table has pawns (2) and if the players have different figures (1).
+        let flags = bytes[4];
+        if (flags & 2 != 0) != material.has_pawns || (flags & 1 != 0) == material.symmetric {
+            return None;
+        }
+
+        let mut table = Table {
+            bytes,
+            parts: Default::default(),
+        };
+        let bytes = &table.bytes;
+
+        let sides = if kind == Kind::Wdl && !material.symmetric {
+            2
+        } else {
+            1
+        };
+        let files = if material.has_pawns { 4 } else { 1 };
+        let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+
+        let mut offset = 5;
+        for file in 0..files {
+            let order = *bytes.get(offset)?;
+            let pawn_order = if both_pawns {
+                *bytes.get(offset + 1)?
+            } else {
+                0xFF
+            };
+            let orders = [
+                [order & 0xF, pawn_order & 0xF],
+                [order >> 4, pawn_order >> 4],
+            ];
+            offset += 1 + both_pawns as usize;
+
+            for piece in 0..material.piece_count {
+                let byte = *bytes.get(offset)?;
+                for side in 0..sides {
+                    table.parts[side][file].pieces[piece] =
+                        if side == 1 { byte >> 4 } else { byte & 0xF };
+                }
+                offset += 1;
+            }
+
+            for (side, &order) in orders.iter().enumerate().take(sides) {
+                let part = &mut table.parts[side][file];
+                let mut pieces = part.pieces[..material.piece_count].to_vec();
+                pieces.sort_unstable();
+                if pieces != material.pieces {
+                    return None;
+                }
+                set_groups(part, material, order, file)?;
+            }
+        }
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                offset = set_sizes(&mut table.parts[side][file], bytes, offset)?;
+            }
+        }
+
+        if kind == Kind::Dtz {
+            for file in 0..files {
+                let part = &mut table.parts[0][file];
+                if part.flags & MAPPED == 0 {
+                    continue;
+                }
+                for map in part.maps.iter_mut() {
+                    if part.flags & WIDE != 0 {
+                        offset += offset & 1;
+                        *map = offset + 2;
+                        offset += 2 * read_u16(bytes, offset) as usize + 2;
+                    } else {
+                        *map = offset + 1;
+                        offset += *bytes.get(offset)? as usize + 1;
+                    }
+                }
+            }
+            offset += offset & 1;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let part = &mut table.parts[side][file];
+                part.sparse_index = offset;
+                offset = offset.checked_add(6 * part.sparse_index_size())?;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let part = &mut table.parts[side][file];
+                part.block_lengths = offset;
+                offset = offset.checked_add(2 * part.block_lengths_size)?;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let part = &mut table.parts[side][file];
+                // The blocks start at multiples of 64 bytes.
+                offset = offset.checked_add(0x3F)? & !0x3F;
+                part.data = offset;
+                offset = offset.checked_add(part.block_count.checked_mul(part.block_size)?)?;
+            }
+        }
+
+        if offset > table.bytes.len() {
+            return None;
+        }
+        Some(table)
+    }
+
+    /// Returns the value with the index `index` in the part, or [`None`] if the blocks of the file are broken.
+    fn decompress(&self, part: &Part, index: u64) -> Option<i32> {
+        if part.flags & SINGLE_VALUE != 0 {
+            return Some(part.min_length as i32);
+        }
+        if index >= part.get_size() {
+            return None;
+        }
+
+        // The sparse index points to the value in the middle of every `span` values, from there the block of the value is searched.
+        let span = part.span as u64;
+        let sparse = part.sparse_index + 6 * (index / span) as usize;
+        let mut block = read_u32(&self.bytes, sparse) as usize;
+        let mut offset = read_u16(&self.bytes, sparse + 4) as i64;
+        offset += (index % span) as i64 - (span / 2) as i64;
+
+        let block_length = |block: usize| {
+            (block < part.block_count)
+                .then(|| read_u16(&self.bytes, part.block_lengths + 2 * block) as i64)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        // The symbols of the block are read one after the other until the one that contains the value.
+        let mut pointer = part.data + block * part.block_size;
+        let mut buffer = read_u64_be(&self.bytes, pointer);
+        pointer += 8;
+        let mut buffer_size = 64;
+
+        let mut symbol;
+        loop {
+            // All codes of a length are consecutive numbers, and longer codes are lower numbers.
+            let mut length = 0;
+            while buffer < part.base[length] {
+                length += 1;
+            }
+            symbol = ((buffer - part.base[length]) >> (64 - length - part.min_length)) as u16;
+            symbol = symbol.wrapping_add(read_u16(&self.bytes, part.lowest_symbols + 2 * length));
+            let symbol_length = *part.symbol_lengths.get(symbol as usize)? as i64;
+
+            if offset < symbol_length + 1 {
+                break;
+            }
+            offset -= symbol_length + 1;
+
+            length += part.min_length;
+            buffer <<= length;
+            buffer_size -= length;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(&self.bytes, pointer) as u64) << (64 - buffer_size);
+                pointer += 4;
+            }
+        }
+
+        // The symbol stands for a pair of symbols, which is followed until the symbol of a single value.
+        let mut symbol = symbol as usize;
+        while part.symbol_lengths[symbol] != 0 {
+            let (left, right) = read_pair(&self.bytes, part.pairs, symbol)?;
+            let left_length = part.symbol_lengths[left] as i64;
+            if offset < left_length + 1 {
+                symbol = left;
+            } else {
+                offset -= left_length + 1;
+                symbol = right;
+            }
+        }
+
+        Some(read_pair(&self.bytes, part.pairs, symbol)?.0 as i32)
+    }
+
+    /// Converts a value of a DTZ table to plies until the next catch or pawn move, `wdl` being the result of the board.
+    /// Returns [`None`] if the value is not in the map.
+    fn map_dtz(&self, part: &Part, value: i32, wdl: i32) -> Option<i32> {
+        let mut value = value;
+
+        if part.flags & MAPPED != 0 {
+            let map = part.maps[[1, 3, 0, 2, 0][(wdl + 2) as usize]];
+            value = if part.flags & WIDE != 0 {
+                let bytes = self.bytes.get(map + 2 * value as usize..)?;
+                u16::from_le_bytes([*bytes.first()?, *bytes.get(1)?]) as i32
+            } else {
+                *self.bytes.get(map + value as usize)? as i32
+            };
+        }
+
+        // The distances are stored in moves unless the table says otherwise.
+        if (wdl == 2 && part.flags & WIN_PLIES == 0)
+            || (wdl == -2 && part.flags & LOSS_PLIES == 0)
+            || wdl.abs() == 1
+        {
+            value *= 2;
+        }
+
+        Some(value + 1)
+    }
+}
+
+impl Part {
+    /// Returns the number of values of the part.
+    fn get_size(&self) -> u64 {
+        self.group_index[self
+            .group_length
+            .iter()
+            .position(|&length| length == 0)
+            .unwrap_or(0)]
+    }
+
+    fn sparse_index_size(&self) -> usize {
+        if self.flags & SINGLE_VALUE != 0 {
+            return 0;
+        }
+        self.get_size().div_ceil(self.span as u64) as usize
+    }
+}
+
+/// Splits the figures of the part into groups of equal figures and calculates the factor of every group in the index.
+///
+/// `order` is the order in which the leading group (`order[0]`) and the remaining pawns (`order[1]`) are encoded among the other groups.
+/// Returns [`None`] if the pawns are not in the first groups.
+fn set_groups(part: &mut Part, material: &Material, order: [u8; 2], file: usize) -> Option<()> {
+    let mut first_length: i32 = if material.has_pawns {
+        0
+    } else if material.has_unique_pieces {
+        3
+    } else {
+        2
+    };
+
+    let mut groups = 0;
+    part.group_length[0] = 1;
+    for i in 1..material.piece_count {
+        first_length -= 1;
+        if first_length > 0 || part.pieces[i] == part.pieces[i - 1] {
+            part.group_length[groups] += 1;
+        } else {
+            groups += 1;
+            part.group_length[groups] = 1;
+        }
+    }
+    groups += 1;
+    part.group_length[groups] = 0;
+
+    let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+    let is_pawn = |index: usize| part.pieces[index] & 7 == 1;
+    if material.has_pawns
+        && (!is_pawn(0)
+            || part.group_length[0] != material.pawn_count[0]
+            || both_pawns
+                && (!is_pawn(part.group_length[0])
+                    || part.group_length[1] != material.pawn_count[1]))
+    {
+        return None;
+    }
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free_squares =
+        64 - part.group_length[0] - if both_pawns { part.group_length[1] } else { 0 };
+    let mut index: u64 = 1;
+
+    let mut k = 0;
+    while next < groups || k == order[0] || k == order[1] {
+        if k == order[0] {
+            part.group_index[0] = index;
+            index *= if material.has_pawns {
+                ENCODING.lead_pawns_size[part.group_length[0]][file]
+            } else if material.has_unique_pieces {
+                31332
+            } else {
+                462
+            };
+        } else if k == order[1] {
+            part.group_index[1] = index;
+            index *= ENCODING.binomial[part.group_length[1]][48 - part.group_length[0]];
+        } else {
+            part.group_index[next] = index;
+            index *= ENCODING.binomial[part.group_length[next]][free_squares];
+            free_squares -= part.group_length[next];
+            next += 1;
+        }
+
+        k += 1;
+        if k > 0xF {
+            return None;
+        }
+    }
+    part.group_index[groups] = index;
+
+    Some(())
+}
+
+/// Reads the sizes and the Huffman code of the part, starting at `offset`. Returns the offset after them.
+fn set_sizes(part: &mut Part, bytes: &[u8], offset: usize) -> Option<usize> {
+    let mut offset = offset;
+    part.flags = *bytes.get(offset)?;
+    offset += 1;
+
+    if part.flags & SINGLE_VALUE != 0 {
+        part.min_length = *bytes.get(offset)? as usize;
+        return Some(offset + 1);
+    }
+
+    let header = bytes.get(offset..offset + 9)?;
+    if header[0] > 31 || header[1] > 31 {
+        return None;
+    }
+    part.block_size = 1 << header[0];
+    part.span = 1 << header[1];
+    let padding = header[2] as usize;
+    part.block_count = u32::from_le_bytes(header[3..7].try_into().unwrap()) as usize;
+    part.block_lengths_size = part.block_count + padding;
+    let max_length = header[7] as usize;
+    part.min_length = header[8] as usize;
+    offset += 9;
+
+    // Longer codes would not fit into the bits that are read at once.
+    if max_length < part.min_length || part.min_length == 0 || max_length > 32 {
+        return None;
+    }
+    part.lowest_symbols = offset;
+
+    // The lowest code of a length is half of the lowest code that is one bit longer, plus the symbols of the length in between.
+    let lengths = max_length - part.min_length + 1;
+    if bytes.len() < offset + 2 * lengths + 2 {
+        return None;
+    }
+    part.base = vec![0; lengths];
+    for i in (0..lengths - 1).rev() {
+        part.base[i] = part.base[i + 1]
+            .wrapping_add(read_u16(bytes, offset + 2 * i) as u64)
+            .wrapping_sub(read_u16(bytes, offset + 2 * (i + 1)) as u64)
+            / 2;
+    }
+    for (i, base) in part.base.iter_mut().enumerate() {
+        *base = base
+            .checked_shl((64 - i - part.min_length) as u32)
+            .unwrap_or(0);
+    }
+    offset += 2 * lengths;
+
+    let symbols = read_u16(bytes, offset) as usize;
+    offset += 2;
+    part.pairs = offset;
+    if bytes.len() < offset + 3 * symbols {
+        return None;
+    }
+
+    part.symbol_lengths = vec![0; symbols];
+    let mut visits = vec![Visit::New; symbols];
+    for symbol in 0..symbols {
+        get_symbol_length(
+            bytes,
+            part.pairs,
+            &mut part.symbol_lengths,
+            &mut visits,
+            symbol,
+        )?;
+    }
+
+    Some(offset + 3 * symbols + (symbols & 1))
+}
+
+/// How far the length of a symbol is counted.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    Started,
+    Done,
+}
+
+/// Counts how many values (minus one) the symbol stands for, and the same for the symbols it consists of.
+/// Returns [`None`] if the symbol consists of symbols that do not exist, or of itself.
+fn get_symbol_length(
+    bytes: &[u8],
+    pairs: usize,
+    lengths: &mut [u32],
+    visits: &mut [Visit],
+    symbol: usize,
+) -> Option<u32> {
+    match visits[symbol] {
+        Visit::New => visits[symbol] = Visit::Started,
+        Visit::Started => return None,
+        Visit::Done => return Some(lengths[symbol]),
+    }
+
+    let (left, right) = read_pair(bytes, pairs, symbol)?;
+    if right != 0xFFF {
+        if left >= lengths.len() || right >= lengths.len() {
+            return None;
+        }
+        let left_length = get_symbol_length(bytes, pairs, lengths, visits, left)?;
+        let right_length = get_symbol_length(bytes, pairs, lengths, visits, right)?;
+        lengths[symbol] = left_length.checked_add(right_length)?.checked_add(1)?;
+    }
+
+    visits[symbol] = Visit::Done;
+    Some(lengths[symbol])
+}
+
+/// Reads the pair of symbols (12 bits each) the symbol stands for. A symbol of a single value stores it as the left symbol.
+fn read_pair(bytes: &[u8], pairs: usize, symbol: usize) -> Option<(usize, usize)> {
+    let pair = bytes.get(pairs + 3 * symbol..pairs + 3 * symbol + 3)?;
+    Some((
+        ((pair[1] as usize & 0xF) << 8) | pair[0] as usize,
+        ((pair[2] as usize) << 4) | (pair[1] as usize >> 4),
+    ))
+}
+
+/// Reads a number from the bytes. Bytes after the end of the file are read as `0`, because the last block may be shorter.
+fn read_bytes<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    let mut number = [0; N];
+    for (i, byte) in number.iter_mut().enumerate() {
+        *byte = bytes.get(offset + i).copied().unwrap_or(0);
+    }
+    number
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(read_bytes(bytes, offset))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(read_bytes(bytes, offset))
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(read_bytes(bytes, offset))
+}
+
+fn read_u64_be(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(read_bytes(bytes, offset))
+}
+
+/// Returns the distance to zeroing of a board whose best move is a catch or a pawn move with the result `wdl`.
+fn get_dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+/// Returns the names of the figures of white and black, e.g. `KRP` and `K`.
+fn get_names(board: &engine::Board) -> (String, String) {
+    let mut names = (String::new(), String::new());
+
+    for (figure, letter) in [
+        (figures::KING, 'K'),
+        (figures::QUEEN, 'Q'),
+        (figures::ROOK, 'R'),
+        (figures::BISHOP, 'B'),
+        (figures::KNIGHT, 'N'),
+        (figures::PAWN, 'P'),
+    ] {
+        for &square in board.squares.iter() {
+            if square != 0 && figures::colored_figure_to_blank_figure(square) == figure {
+                if square < 10 {
+                    names.0.push(letter);
+                } else {
+                    names.1.push(letter);
+                }
+            }
+        }
+    }
+
+    names
+}
+
+/// Converts a figure to the numbering of the tables: pawn 1, knight 2, bishop 3, rook 4, queen 5, king 6, plus 8 for black.
+fn get_piece(figure: figures::Figure) -> u8 {
+    let piece = match figures::colored_figure_to_blank_figure(figure) {
+        figures::PAWN => 1,
+        figures::KNIGHT => 2,
+        figures::BISHOP => 3,
+        figures::ROOK => 4,
+        figures::QUEEN => 5,
+        _ => 6,
+    };
+    if figure < 10 {
+        piece
+    } else {
+        piece + 8
+    }
+}
+
+/// Returns how far the square is above (positive) or below (negative) the diagonal a1-h8.
+const fn get_diagonal(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+/// Encodes the first three figures together, the first one being in the triangle a1-d1-d4.
+/// If figures are on the diagonal a1-d4, the next figure not on it is below it.
+fn get_unique_pieces_index(squares: &[usize; MAX_PIECES]) -> u64 {
+    let (first, second, third) = (squares[0], squares[1], squares[2]);
+    let adjust1 = (second > first) as u64;
+    let adjust2 = (third > first) as u64 + (third > second) as u64;
+    let row = |square: usize| (square / 8) as u64;
+
+    if get_diagonal(first) != 0 {
+        (ENCODING.map_a1d1d4[first] * 63 + (second as u64 - adjust1)) * 62 + third as u64 - adjust2
+    } else if get_diagonal(second) != 0 {
+        (6 * 63 + row(first) * 28 + ENCODING.map_b1h1h7[second]) * 62 + third as u64 - adjust2
+    } else if get_diagonal(third) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + row(first) * 7 * 28
+            + (row(second) - adjust1) * 28
+            + ENCODING.map_b1h1h7[third]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + row(first) * 7 * 6
+            + (row(second) - adjust1) * 6
+            + (row(third) - adjust2)
+    }
+}
+
+/// The numberings of squares and combinations the tables use for the index of a board.
+struct Encoding {
+    /// Numbers the squares a2 to h7 from 47 down to 0, so that the leading pawn, the one closest to the edge and then to the first row, has the highest number.
+    map_pawns: [u64; 64],
+    /// Numbers the squares below the diagonal a1-h8 from 0 to 27.
+    map_b1h1h7: [u64; 64],
+    /// Numbers the squares of the triangle a1-d1-d4 from 0 to 9, the squares on the diagonal last.
+    map_a1d1d4: [u64; 64],
+    /// Numbers the 462 legal placements of two kings, indexed by the number of the first king in [`Encoding::map_a1d1d4`] and the square of the second one.
+    map_kk: [[u64; 64]; 10],
+    /// The number of ways to choose `k` of `n` squares, indexed by `k` and `n`.
+    binomial: [[u64; 64]; MAX_PIECES],
+    /// The index of the leading pawns by their number and the square of the leading pawn.
+    lead_pawn_index: [[u64; 64]; 6],
+    /// The number of placements of the leading pawns by their number and the file of the leading pawn.
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+static ENCODING: Encoding = Encoding::new();
+
+impl Encoding {
+    const fn new() -> Encoding {
+        let mut encoding = Encoding {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            lead_pawn_index: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        let mut code = 0;
+        let mut square = 0;
+        while square < 64 {
+            if get_diagonal(square) < 0 {
+                encoding.map_b1h1h7[square] = code;
+                code += 1;
+            }
+            square += 1;
+        }
+
+        // The squares below the diagonal first, then the squares on it.
+        code = 0;
+        let mut on_diagonal = 0;
+        while on_diagonal < 2 {
+            square = 0;
+            while square < 28 {
+                if square % 8 <= 3
+                    && (get_diagonal(square) == 0) == (on_diagonal == 1)
+                    && get_diagonal(square) <= 0
+                {
+                    encoding.map_a1d1d4[square] = code;
+                    code += 1;
+                }
+                square += 1;
+            }
+            on_diagonal += 1;
+        }
+
+        // If the first king is on the diagonal, the second one must not be above it. Placements with both kings on the diagonal are numbered last.
+        code = 0;
+        let mut both_on_diagonal = 0;
+        while both_on_diagonal < 2 {
+            let mut index = 0;
+            while index < 10 {
+                let mut first = 0;
+                while first < 28 {
+                    if encoding.map_a1d1d4[first] == index as u64 && (index != 0 || first == 1) {
+                        let mut second = 0;
+                        while second < 64 {
+                            let (dx, dy) = (
+                                (first % 8) as i32 - (second % 8) as i32,
+                                (first / 8) as i32 - (second / 8) as i32,
+                            );
+                            let touching = dx.abs() <= 1 && dy.abs() <= 1;
+                            let diagonal = get_diagonal(first) == 0;
+                            let above = diagonal && get_diagonal(second) > 0;
+                            if !touching
+                                && !above
+                                && (diagonal && get_diagonal(second) == 0)
+                                    == (both_on_diagonal == 1)
+                            {
+                                encoding.map_kk[index][second] = code;
+                                code += 1;
+                            }
+                            second += 1;
+                        }
+                    }
+                    first += 1;
+                }
+                index += 1;
+            }
+            both_on_diagonal += 1;
+        }
+
+        encoding.binomial[0][0] = 1;
+        let mut n = 1;
+        while n < 64 {
+            let mut k = 0;
+            while k < MAX_PIECES && k <= n {
+                encoding.binomial[k][n] = if k > 0 {
+                    encoding.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n {
+                    encoding.binomial[k][n - 1]
+                } else {
+                    0
+                };
+                k += 1;
+            }
+            n += 1;
+        }
+
+        // The squares of the other pawns are counted from the ones left when the leading pawn is on the square.
+        let mut available = 47;
+        let mut lead_pawns = 1;
+        while lead_pawns <= 5 {
+            let mut file = 0;
+            while file < 4 {
+                let mut index = 0;
+                let mut row = 1;
+                while row <= 6 {
+                    let square = 8 * row + file;
+                    if lead_pawns == 1 {
+                        encoding.map_pawns[square] = available;
+                        encoding.map_pawns[square ^ 7] = available.saturating_sub(1);
+                        available = available.saturating_sub(2);
+                    }
+                    encoding.lead_pawn_index[lead_pawns][square] = index;
+                    index += encoding.binomial[lead_pawns - 1][encoding.map_pawns[square] as usize];
+                    row += 1;
+                }
+                encoding.lead_pawns_size[lead_pawns][file] = index;
+                file += 1;
+            }
+            lead_pawns += 1;
+        }
+
+        encoding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::fen;
+    use crate::pgn;
+
+    /// The tables KQvK and KRvK.
+    const DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/syzygy");
+
+    fn probe(tablebases: &Tablebases, fen: &str) -> (Option<Wdl>, Option<i32>) {
+        let game = fen::from_fen(fen).unwrap();
+        (
+            tablebases.probe_wdl(
+                game.board,
+                game.black_figures,
+                game.white_figures,
+                game.white,
+            ),
+            tablebases.probe_dtz(
+                game.board,
+                game.black_figures,
+                game.white_figures,
+                game.white,
+            ),
+        )
+    }
+
+    /// Returns the move chosen with the tables, the result and the distance to zeroing of the opponent after the move.
+    fn probe_root(tablebases: &Tablebases, fen: &str) -> (String, Wdl, Option<i32>) {
+        let game = fen::from_fen(fen).unwrap();
+        let (board, black_figures, white_figures, white) = (
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+        );
+        let (r#move, wdl) = tablebases
+            .probe_root(board, black_figures, white_figures, white)
+            .unwrap();
+
+        let (next_board, next_black_figures, next_white_figures) =
+            engine::move_figure(board, black_figures, white_figures, r#move, white);
+        (
+            pgn::get_san(board, black_figures, white_figures, white, r#move),
+            wdl,
+            tablebases.probe_dtz(next_board, next_black_figures, next_white_figures, !white),
+        )
+    }
+
+    #[test]
+    fn encoding_numbers_every_placement_once() {
+        // The two kings can be placed in 462 ways, the first one in the triangle a1-d1-d4.
+        let mut codes: Vec<u64> = Vec::new();
+        for (index, first) in [1, 2, 3, 10, 11, 19, 0, 9, 18, 27].into_iter().enumerate() {
+            assert_eq!(ENCODING.map_a1d1d4[first], index as u64);
+            for second in 0..64usize {
+                let touching =
+                    (first % 8).abs_diff(second % 8) <= 1 && (first / 8).abs_diff(second / 8) <= 1;
+                let above = get_diagonal(first) == 0 && get_diagonal(second) > 0;
+                if !touching && !above {
+                    codes.push(ENCODING.map_kk[index][second]);
+                }
+            }
+        }
+        codes.sort_unstable();
+        assert_eq!(codes, (0..462).collect::<Vec<_>>());
+
+        let mut pawns: Vec<u64> = (8..56).map(|square| ENCODING.map_pawns[square]).collect();
+        pawns.sort_unstable();
+        assert_eq!(pawns, (0..48).collect::<Vec<_>>());
+        assert_eq!(ENCODING.map_pawns[8], 47);
+        assert_eq!(ENCODING.lead_pawns_size[1], [6; 4]);
+        assert_eq!(ENCODING.binomial[2][62], 62 * 61 / 2);
+    }
+
+    #[test]
+    fn names_are_parsed() {
+        let material = Material::parse("KRPvKR").unwrap();
+        assert_eq!(material.piece_count, 5);
+        assert!(material.has_pawns && material.has_unique_pieces && !material.symmetric);
+        assert_eq!(material.pawn_count, [1, 0]);
+
+        let material = Material::parse("KPvKPP").unwrap();
+        assert_eq!(material.pawn_count, [1, 2]);
+        assert!(Material::parse("KQvKQ").unwrap().symmetric);
+        assert!(!Material::parse("KRRvKBB").unwrap().has_unique_pieces);
+        assert!(Material::parse("KQQ").is_none());
+        assert!(Material::parse("KXvK").is_none());
+
+        let game = engine::fen::from_fen("8/8/8/4k3/8/8/2P5/R3K3 w - - 0 1").unwrap();
+        assert_eq!(get_names(&game.board), ("KRP".to_string(), "K".to_string()));
+    }
+
+    #[test]
+    fn tables_store_results_and_distances() {
+        let tablebases = Tablebases::open(DIRECTORY).unwrap();
+        assert_eq!(tablebases.len(), 2);
+        assert_eq!(tablebases.get_max_pieces(), 3);
+
+        for (fen, wdl, dtz) in [
+            ("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1", Wdl::Win, 1),
+            ("8/8/8/3k4/8/8/8/K5Q1 w - - 0 1", Wdl::Win, 17),
+            ("8/8/8/3k4/8/8/8/K5Q1 b - - 0 1", Wdl::Loss, -18),
+            ("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1", Wdl::Loss, -1),
+            // Stalemate and a queen that can be caught.
+            ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", Wdl::Draw, 0),
+            ("8/8/8/8/8/8/6Qk/K7 b - - 0 1", Wdl::Draw, 0),
+            ("8/8/8/4k3/8/8/8/R3K3 w - - 0 1", Wdl::Win, 27),
+            ("8/8/8/4k3/8/8/8/R3K3 b - - 0 1", Wdl::Loss, -28),
+            // Black has the stronger figures.
+            ("r3k3/8/8/8/4K3/8/8/8 b - - 0 1", Wdl::Win, 27),
+            ("8/8/8/8/8/2k5/8/K5r1 w - - 0 1", Wdl::Loss, -4),
+            ("8/8/8/8/8/1k6/8/K5r1 w - - 0 1", Wdl::Loss, -1),
+            ("8/8/8/8/8/8/1k6/6rK w - - 0 1", Wdl::Draw, 0),
+        ] {
+            assert_eq!(probe(&tablebases, fen), (Some(wdl), Some(dtz)), "{}", fen);
+        }
+
+        assert_eq!(
+            probe(&tablebases, "8/8/8/3k4/8/8/8/KQ1N4 w - - 0 1"),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn best_moves_are_chosen_from_the_tables() {
+        let tablebases = Tablebases::open(DIRECTORY).unwrap();
+
+        assert_eq!(
+            probe_root(&tablebases, "7k/8/6K1/8/8/8/8/1Q6 w - - 0 1"),
+            ("Qb8#".to_string(), Wdl::Win, Some(-1))
+        );
+        let (san, wdl, _) = probe_root(&tablebases, "8/8/8/8/8/8/6Qk/K7 b - - 0 1");
+        assert_eq!((san.as_str(), wdl), ("Kxg2", Wdl::Draw));
+
+        // The fastest win and the slowest loss.
+        let (_, wdl, dtz) = probe_root(&tablebases, "8/8/8/4k3/8/8/8/R3K3 w - - 0 1");
+        assert_eq!((wdl, dtz), (Wdl::Win, Some(-26)));
+        let (_, wdl, dtz) = probe_root(&tablebases, "8/8/8/4k3/8/8/8/R3K3 b - - 0 1");
+        assert_eq!((wdl, dtz), (Wdl::Loss, Some(27)));
+
+        // The win comes too late for the fifty-move rule.
+        let (_, wdl, dtz) = probe_root(&tablebases, "8/8/8/4k3/8/8/8/R3K3 w - - 90 1");
+        assert_eq!((wdl, dtz), (Wdl::CursedWin, Some(-26)));
+    }
+
+    #[test]
+    fn broken_tables_are_rejected() {
+        let material = Material::parse("KRvK").unwrap();
+        let bytes = std::fs::read(format!("{}/KRvK.rtbw", DIRECTORY)).unwrap();
+        let table = Table::parse(bytes.clone(), &material, Kind::Wdl).unwrap();
+        assert!(Table::parse(bytes[..bytes.len() / 2].to_vec(), &material, Kind::Wdl).is_none());
+        assert!(Table::parse(bytes.clone(), &material, Kind::Dtz).is_none());
+        assert!(
+            Table::parse(bytes.clone(), &Material::parse("KQvK").unwrap(), Kind::Wdl).is_none()
+        );
+
+        // Any byte of the header or the blocks may be wrong, but the table must never panic.
+        let data = table.parts[1][0].data;
+        for offset in (0..data).chain((data..bytes.len()).step_by(7)) {
+            for change in [0x01, 0x80, 0xFF] {
+                let mut bytes = bytes.clone();
+                bytes[offset] ^= change;
+                if let Some(table) = Table::parse(bytes, &material, Kind::Wdl) {
+                    for part in &table.parts {
+                        for index in (0..part[0].get_size()).step_by(101) {
+                            table.decompress(&part[0], index);
+                        }
+                    }
+                }
+            }
+        }
+
+        let directory =
+            std::env::temp_dir().join(format!("chess_ai_syzygy_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("KRvK.rtbw"), &bytes[..100]).unwrap();
+        let result = Tablebases::open(directory.to_str().unwrap());
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(result.unwrap_err().contains("KRvK.rtbw"));
+    }
+}
@@ -0,0 +1,262 @@
+//! A transposition table: a fixed-size cache of search results, indexed by the [`engine::zobrist`] key of the board
+//! (see https://www.chessprogramming.org/Transposition_Table).
+//!
+//! An entry is stored in two atomic integers, the key XORed with the data and the data,
+//! so that an entry that was written by two threads at the same time is not found instead of being wrong
+//! (see https://www.chessprogramming.org/Shared_Hash_Table#Lockless).
+
+use crate::engine;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The default size of the table in megabytes.
+pub const DEFAULT_SIZE: usize = 64;
+
+/// What the stored value says about the real value of the board (from the view of white).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    /// The value is the real value.
+    Exact,
+    /// The real value is at least the value (the search was cut off, because white could reach too much).
+    Lower,
+    /// The real value is at most the value (the search was cut off, because black could reach too much).
+    Upper,
+}
+
+/// The result of a search of a board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    /// The number of plies the board was searched with.
+    pub depth: u8,
+    pub value: i32,
+    pub bound: Bound,
+    /// The best move found, or [`engine::moves::NULL_MOVE`] if none was found.
+    pub best_move: engine::Move,
+}
+
+pub struct TranspositionTable {
+    entries: Vec<[AtomicU64; 2]>,
+    probes: AtomicU64,
+    hits: AtomicU64,
+}
+
+impl TranspositionTable {
+    /// Creates an empty table that uses at most `megabytes` megabytes.
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        // The number of entries is a power of two, so that the index is just the lowest bits of the key.
+        let entries = megabytes.max(1) * 1024 * 1024 / std::mem::size_of::<[AtomicU64; 2]>();
+        let entries = 1 << (usize::BITS - 1 - entries.leading_zeros());
+
+        TranspositionTable {
+            entries: (0..entries)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+            probes: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    /// Removes all entries and resets the statistics.
+    pub fn clear(&self) {
+        for entry in &self.entries {
+            entry[0].store(0, Ordering::Relaxed);
+            entry[1].store(0, Ordering::Relaxed);
+        }
+        self.reset_statistics();
+    }
+
+    /// Returns the entry of the board with the given key, if there is one.
+    pub fn probe(&self, hash: u64) -> Option<Entry> {
+        self.probes.fetch_add(1, Ordering::Relaxed);
+
+        let entry = &self.entries[self.get_index(hash)];
+        let key = entry[0].load(Ordering::Relaxed);
+        let data = entry[1].load(Ordering::Relaxed);
+        if key ^ data != hash {
+            return None;
+        }
+
+        let entry = unpack(data)?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(entry)
+    }
+
+    /// Stores the entry of the board with the given key.
+    ///
+    /// An entry of the same board is only replaced by an entry that was searched at least as deep, entries of other boards are always replaced.
+    pub fn store(&self, hash: u64, entry: Entry) {
+        let slot = &self.entries[self.get_index(hash)];
+        let key = slot[0].load(Ordering::Relaxed);
+        let data = slot[1].load(Ordering::Relaxed);
+        if key ^ data == hash && unpack(data).is_some_and(|old| old.depth > entry.depth) {
+            return;
+        }
+
+        let data = pack(entry);
+        slot[0].store(hash ^ data, Ordering::Relaxed);
+        slot[1].store(data, Ordering::Relaxed);
+    }
+
+    /// Returns the number of probes and the number of probes that found an entry since the last reset.
+    pub fn get_statistics(&self) -> (u64, u64) {
+        (
+            self.probes.load(Ordering::Relaxed),
+            self.hits.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn reset_statistics(&self) {
+        self.probes.store(0, Ordering::Relaxed);
+        self.hits.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns how many of the first 1000 entries are used, in permille (like `hashfull` in the UCI).
+    pub fn get_fullness(&self) -> usize {
+        let sample = self.entries.len().min(1000);
+        let used = self.entries[..sample]
+            .iter()
+            .filter(|entry| entry[1].load(Ordering::Relaxed) != 0)
+            .count();
+        used * 1000 / sample
+    }
+
+    fn get_index(&self, hash: u64) -> usize {
+        (hash as usize) & (self.entries.len() - 1)
+    }
+}
+
+/// Converts a value of [`super::min_max`] at a board `ply` plies after the start of the search to a value that can be stored.
+///
+/// A checkmate is valued by its distance to the start of the search, so its value is stored relative to the stored board instead.
+pub fn to_table_value(value: i32, ply: u8) -> i32 {
+    if value > i32::MAX - 1000 {
+        value + ply as i32
+    } else if value < i32::MIN + 1000 {
+        value - ply as i32
+    } else {
+        value
+    }
+}
+
+/// Converts a stored value back to a value of [`super::min_max`] at a board `ply` plies after the start of the search, see [`to_table_value`].
+pub fn from_table_value(value: i32, ply: u8) -> i32 {
+    if value > i32::MAX - 1000 {
+        value - ply as i32
+    } else if value < i32::MIN + 1000 {
+        value + ply as i32
+    } else {
+        value
+    }
+}
+
+/// Packs an entry into 62 bits:
+/// the value (32 bits), the depth (8 bits), the bound (2 bits), and the start position (6 bits), target position (6 bits), promotion (3 bits) and flags (3 bits) of the best move.
+fn pack(entry: Entry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+
+    entry.value as u32 as u64
+        | (entry.depth as u64) << 32
+        | bound << 40
+        | (entry.best_move.from as u64) << 42
+        | (entry.best_move.to as u64) << 48
+        | (entry.best_move.promotion as u64) << 54
+        | (entry.best_move.flags as u64) << 59
+}
+
+/// Unpacks an entry packed with [`pack`]. Returns [`None`] for an empty entry.
+fn unpack(data: u64) -> Option<Entry> {
+    let bound = match (data >> 40) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+
+    Some(Entry {
+        depth: (data >> 32) as u8,
+        value: data as u32 as i32,
+        bound,
+        best_move: engine::Move {
+            from: ((data >> 42) & 63) as u8,
+            to: ((data >> 48) & 63) as u8,
+            promotion: ((data >> 54) & 7) as u8,
+            flags: ((data >> 59) & 7) as u8,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_survive_packing() {
+        for value in [i32::MIN, i32::MIN + 1, -1, 0, 1, i32::MAX - 1, i32::MAX] {
+            for depth in [0, 1, u8::MAX] {
+                for bound in [Bound::Exact, Bound::Lower, Bound::Upper] {
+                    for best_move in [
+                        engine::moves::NULL_MOVE,
+                        engine::Move {
+                            from: 63,
+                            to: 63,
+                            promotion: 7,
+                            flags: 7,
+                        },
+                    ] {
+                        let entry = Entry {
+                            depth,
+                            value,
+                            bound,
+                            best_move,
+                        };
+                        assert_eq!(unpack(pack(entry)), Some(entry));
+                    }
+                }
+            }
+        }
+
+        assert_eq!(unpack(0), None);
+    }
+
+    #[test]
+    fn checkmates_are_stored_relative_to_the_board() {
+        // A checkmate 10 plies after the start of the search, found at a board 4 plies after the start.
+        // Found again 6 plies after the start, the checkmate is 2 plies further away.
+        for value in [i32::MAX - 100 - 10, i32::MIN + 100 + 10] {
+            let stored = to_table_value(value, 4);
+            let probed = from_table_value(stored, 6);
+            assert_eq!(probed, if value > 0 { value - 2 } else { value + 2 });
+            assert_eq!(from_table_value(stored, 4), value);
+        }
+
+        for value in [-999, 0, 999] {
+            assert_eq!(from_table_value(to_table_value(value, 4), 6), value);
+        }
+    }
+
+    #[test]
+    fn other_boards_are_not_found() {
+        let table = TranspositionTable::new(1);
+        let entry = Entry {
+            depth: 3,
+            value: 42,
+            bound: Bound::Lower,
+            best_move: engine::moves::NULL_MOVE,
+        };
+
+        // Both keys have the same index in the table.
+        let hash = 0x1234_5678_9abc_def0;
+        let other = hash ^ (1 << 63);
+        table.store(hash, entry);
+        assert_eq!(table.probe(hash), Some(entry));
+        assert_eq!(table.probe(other), None);
+
+        // An entry written by two threads at the same time: the key of one entry and the data of another.
+        let slot = &table.entries[table.get_index(hash)];
+        slot[1].store(pack(Entry { value: 7, ..entry }), Ordering::Relaxed);
+        assert_eq!(table.probe(hash), None);
+    }
+}
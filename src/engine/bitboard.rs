@@ -0,0 +1,159 @@
+//! Bitboards: sets of positions stored in a `u64`, bit `8 * (y - 1) + (x - 1)` stands for the position `(x, y)`.
+//!
+//! The attacks of knights, kings and pawns are read from tables that are calculated at compile time.
+//! The attacks of bishops, rooks and queens are calculated with rays, that are cut off at the first figure in their way
+//! (see https://www.chessprogramming.org/Classical_Approach).
+
+pub type Bitboard = u64;
+
+/// Returns the bitboard with only the given position set.
+pub const fn bit(x: u8, y: u8) -> Bitboard {
+    1 << (8 * (y - 1) + (x - 1))
+}
+
+/// Returns an iterator over the indices (`8 * (y - 1) + (x - 1)`) of all positions in the bitboard.
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = u8> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let square = bitboard.trailing_zeros() as u8;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+/// Converts the coordinates of a position to its index.
+pub const fn to_square(x: u8, y: u8) -> u8 {
+    8 * (y - 1) + (x - 1)
+}
+
+/// Converts the index of a position to its coordinates.
+pub const fn to_position(square: u8) -> (u8, u8) {
+    (square % 8 + 1, square / 8 + 1)
+}
+
+const fn generate_attacks(directions: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut attacks = [0; 64];
+
+    let mut square = 0;
+    while square < 64 {
+        let x = (square % 8) as i8;
+        let y = (square / 8) as i8;
+
+        let mut i = 0;
+        while i < directions.len() {
+            let target_x = x + directions[i].0;
+            let target_y = y + directions[i].1;
+            if target_x >= 0 && target_x < 8 && target_y >= 0 && target_y < 8 {
+                attacks[square] |= 1 << (8 * target_y + target_x);
+            }
+            i += 1;
+        }
+
+        square += 1;
+    }
+
+    attacks
+}
+
+const fn generate_rays() -> [[Bitboard; 64]; 8] {
+    let mut rays = [[0; 64]; 8];
+
+    let mut direction = 0;
+    while direction < 8 {
+        let (step_x, step_y) = DIRECTIONS[direction];
+
+        let mut square = 0;
+        while square < 64 {
+            let mut x = (square % 8) as i8 + step_x;
+            let mut y = (square / 8) as i8 + step_y;
+            while x >= 0 && x < 8 && y >= 0 && y < 8 {
+                rays[direction][square] |= 1 << (8 * y + x);
+                x += step_x;
+                y += step_y;
+            }
+            square += 1;
+        }
+
+        direction += 1;
+    }
+
+    rays
+}
+
+pub static KNIGHT_ATTACKS: [Bitboard; 64] = generate_attacks(&[
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+]);
+
+pub static KING_ATTACKS: [Bitboard; 64] = generate_attacks(&[
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+]);
+
+/// The positions a pawn catches on, index 0 for white pawns and 1 for black pawns.
+pub static PAWN_ATTACKS: [[Bitboard; 64]; 2] = [
+    generate_attacks(&[(-1, 1), (1, 1)]),
+    generate_attacks(&[(-1, -1), (1, -1)]),
+];
+
+/// The directions of the rays. The first four go to higher indices, the last four to lower indices.
+const DIRECTIONS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (-1, 1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (1, -1),
+];
+
+static RAYS: [[Bitboard; 64]; 8] = generate_rays();
+
+/// Returns the positions on the ray in the given direction up to (and including) the first occupied position.
+fn ray_attacks(direction: usize, square: u8, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][square as usize];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+
+    let blocker = if direction < 4 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[direction][blocker as usize]
+}
+
+pub fn bishop_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    ray_attacks(1, square, occupied)
+        | ray_attacks(3, square, occupied)
+        | ray_attacks(5, square, occupied)
+        | ray_attacks(7, square, occupied)
+}
+
+pub fn rook_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    ray_attacks(0, square, occupied)
+        | ray_attacks(2, square, occupied)
+        | ray_attacks(4, square, occupied)
+        | ray_attacks(6, square, occupied)
+}
+
+pub fn queen_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
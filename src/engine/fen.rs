@@ -0,0 +1,424 @@
+use super::figures;
+use super::{Board, Game, Positions};
+
+/// The FEN of the initial board.
+pub const INITIAL_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Parses a position in the Forsyth–Edwards Notation.
+///
+/// The halfmove clock and the move number may be omitted, they default to `0` and `1`.
+pub fn from_fen(fen: &str) -> Result<Game, String> {
+    let mut fields = fen.split_whitespace();
+
+    let placement = fields.next().ok_or("The FEN is empty")?;
+    let side = fields.next().unwrap_or("w");
+    let castling = fields.next().unwrap_or("-");
+    let en_passant = fields.next().unwrap_or("-");
+    let halfmove_clock = fields.next().unwrap_or("0");
+    let fullmove = fields.next().unwrap_or("1");
+
+    if fields.next().is_some() {
+        return Err("The FEN has too many fields".to_string());
+    }
+
+    let mut board = super::new_board([0; 64], [0, 0]);
+
+    let rows: Vec<&str> = placement.split('/').collect();
+    if rows.len() != 8 {
+        return Err(format!("Expected 8 rows, found {}", rows.len()));
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        let y = 8 - i as u8;
+        let mut x: u8 = 1;
+
+        for r#char in row.chars() {
+            if let Some(empty) = r#char.to_digit(10) {
+                x = x
+                    .checked_add(empty as u8)
+                    .filter(|&x| x <= 9)
+                    .ok_or_else(|| format!("Row {} contains more than 8 positions", y))?;
+                continue;
+            }
+
+            let figure = char_to_figure(r#char)
+                .ok_or_else(|| format!("Unknown figure '{}' in row {}", r#char, y))?;
+
+            if x > 8 {
+                return Err(format!("Row {} contains more than 8 positions", y));
+            }
+
+            if figures::colored_figure_to_blank_figure(figure) == figures::PAWN
+                && (y == 1 || y == 8)
+            {
+                return Err(format!("A pawn can not stand in row {}", y));
+            }
+
+            board = super::set_figure(board, figure, x, y);
+            x += 1;
+        }
+
+        if x != 9 {
+            return Err(format!("Row {} does not contain 8 positions", y));
+        }
+    }
+
+    let white = match side {
+        "w" => true,
+        "b" => false,
+        _ => return Err(format!("Unknown player '{}'", side)),
+    };
+
+    if castling != "-" {
+        for r#char in castling.chars() {
+            match r#char {
+                'K' => board.castling[0] |= 2,
+                'Q' => board.castling[0] |= 1,
+                'k' => board.castling[1] |= 2,
+                'q' => board.castling[1] |= 1,
+                _ => return Err(format!("Unknown castling right '{}'", r#char)),
+            }
+        }
+    }
+
+    // A castling right is only kept if the king and the rook are still on their initial positions.
+    for (index, y, king, rook) in [
+        (0, 1, figures::WHITE_KING, figures::WHITE_ROOK),
+        (1, 8, figures::BLACK_KING, figures::BLACK_ROOK),
+    ] {
+        if super::get_figure(board, 5, y) != king {
+            board.castling[index] = 0;
+        }
+        if super::get_figure(board, 1, y) != rook {
+            board.castling[index] &= !1;
+        }
+        if super::get_figure(board, 8, y) != rook {
+            board.castling[index] &= !2;
+        }
+    }
+
+    if en_passant != "-" {
+        let (x, y) = parse_position(en_passant)
+            .filter(|&(_, y)| y == 3 || y == 6)
+            .ok_or_else(|| format!("Invalid en passant position '{}'", en_passant))?;
+
+        // Like after a move, the position is only remembered if a pawn of the player to move can catch en passant,
+        // otherwise the same board would have another key.
+        let (skipped_y, pawn_y, pawn, opponent_pawn) = if white {
+            (6, 5, figures::BLACK_PAWN, figures::WHITE_PAWN)
+        } else {
+            (3, 4, figures::WHITE_PAWN, figures::BLACK_PAWN)
+        };
+        if y == skipped_y
+            && super::get_figure(board, x, pawn_y) == pawn
+            && ((x > 1 && super::get_figure(board, x - 1, pawn_y) == opponent_pawn)
+                || (x < 8 && super::get_figure(board, x + 1, pawn_y) == opponent_pawn))
+        {
+            board.en_passant = 8 * (y - 1) + (x - 1);
+        }
+    }
+
+    board.halfmove_clock = halfmove_clock
+        .parse::<u16>()
+        .map_err(|_| format!("Invalid halfmove clock '{}'", halfmove_clock))?
+        .min(255) as u8;
+
+    let fullmove = fullmove
+        .parse::<u16>()
+        .map_err(|_| format!("Invalid move number '{}'", fullmove))?
+        .max(1);
+
+    board.hash = super::zobrist::get_hash(&board, white);
+
+    let white_figures = get_positions(board, true)?;
+    let black_figures = get_positions(board, false)?;
+
+    let mut game = Game {
+        board,
+        black_figures,
+        white_figures,
+        white,
+        fullmove,
+        history: vec![board],
+        start_fen: String::new(),
+        moves: Vec::new(),
+    };
+    game.start_fen = to_fen(&game);
+
+    Ok(game)
+}
+
+/// Converts the current board of `game` to the Forsyth–Edwards Notation.
+pub fn to_fen(game: &Game) -> String {
+    let board = game.board;
+    let mut fen = String::new();
+
+    for y in (1..=8).rev() {
+        let mut empty = 0;
+
+        for x in 1..=8 {
+            let figure = super::get_figure(board, x, y);
+            if figure == 0 {
+                empty += 1;
+                continue;
+            }
+
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+                empty = 0;
+            }
+            fen.push(figure_to_char(figure));
+        }
+
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if y > 1 {
+            fen.push('/');
+        }
+    }
+
+    fen.push_str(if game.white { " w " } else { " b " });
+
+    let mut castling = String::new();
+    if board.castling[0] & 2 != 0 {
+        castling.push('K');
+    }
+    if board.castling[0] & 1 != 0 {
+        castling.push('Q');
+    }
+    if board.castling[1] & 2 != 0 {
+        castling.push('k');
+    }
+    if board.castling[1] & 1 != 0 {
+        castling.push('q');
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    fen.push_str(&castling);
+
+    if board.en_passant == 0 {
+        fen.push_str(" -");
+    } else {
+        fen.push_str(&format!(
+            " {}{}",
+            (b'a' + board.en_passant % 8) as char,
+            board.en_passant / 8 + 1
+        ));
+    }
+
+    fen.push_str(&format!(" {} {}", board.halfmove_clock, game.fullmove));
+
+    fen
+}
+
+/// Parses a position like `e3` to its coordinates.
+pub fn parse_position(position: &str) -> Option<(u8, u8)> {
+    let bytes = position.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+
+    let x = bytes[0].to_ascii_lowercase().wrapping_sub(b'a') + 1;
+    let y = bytes[1].wrapping_sub(b'0');
+
+    if (1..=8).contains(&x) && (1..=8).contains(&y) {
+        Some((x, y))
+    } else {
+        None
+    }
+}
+
+pub fn char_to_figure(r#char: char) -> Option<figures::Figure> {
+    Some(match r#char {
+        'K' => figures::WHITE_KING,
+        'Q' => figures::WHITE_QUEEN,
+        'N' => figures::WHITE_KNIGHT,
+        'B' => figures::WHITE_BISHOP,
+        'R' => figures::WHITE_ROOK,
+        'P' => figures::WHITE_PAWN,
+        'k' => figures::BLACK_KING,
+        'q' => figures::BLACK_QUEEN,
+        'n' => figures::BLACK_KNIGHT,
+        'b' => figures::BLACK_BISHOP,
+        'r' => figures::BLACK_ROOK,
+        'p' => figures::BLACK_PAWN,
+        _ => return None,
+    })
+}
+
+pub fn figure_to_char(figure: figures::Figure) -> char {
+    match figure {
+        figures::WHITE_KING => 'K',
+        figures::WHITE_QUEEN => 'Q',
+        figures::WHITE_KNIGHT => 'N',
+        figures::WHITE_BISHOP => 'B',
+        figures::WHITE_ROOK => 'R',
+        figures::WHITE_PAWN => 'P',
+        figures::BLACK_KING => 'k',
+        figures::BLACK_QUEEN => 'q',
+        figures::BLACK_KNIGHT => 'n',
+        figures::BLACK_BISHOP => 'b',
+        figures::BLACK_ROOK => 'r',
+        figures::BLACK_PAWN => 'p',
+        _ => ' ',
+    }
+}
+
+/// Builds the [`Positions`] of one player from the board.
+///
+/// Every figure gets the index it has in [`super::POSITIONS_WHITE`] and [`super::POSITIONS_BLACK`].
+/// Additional figures (from promotions) are stored in a free index behind that, because [`super::make_move`]
+/// only searches a figure from its first index on.
+fn get_positions(board: Board, white: bool) -> Result<Positions, String> {
+    let color = if white { 0 } else { 10 };
+    let mut positions: Positions = [(255, 1, 1); 16];
+    let mut additional = Vec::new();
+
+    // Pawns first, so that additional figures can only use the indices of pawns which do not exist.
+    for blank_figure in [
+        figures::PAWN,
+        figures::KING,
+        figures::QUEEN,
+        figures::KNIGHT,
+        figures::BISHOP,
+        figures::ROOK,
+    ] {
+        let indices = get_indices(blank_figure);
+
+        for i in 0..64u8 {
+            if board.squares[i as usize] != blank_figure + color {
+                continue;
+            }
+
+            let position = (blank_figure + color, i % 8 + 1, i / 8 + 1);
+            match indices.clone().find(|&index| positions[index].0 == 255) {
+                Some(index) => positions[index] = position,
+                None if blank_figure == figures::PAWN || blank_figure == figures::KING => {
+                    return Err(format!(
+                        "{} has too many {}",
+                        if white { "White" } else { "Black" },
+                        if blank_figure == figures::PAWN {
+                            "pawns"
+                        } else {
+                            "kings"
+                        }
+                    ))
+                }
+                None => additional.push(position),
+            }
+        }
+    }
+
+    for position in additional {
+        let start = get_indices(position.0 - color).start;
+        let index = (start..16)
+            .find(|&index| positions[index].0 == 255)
+            .ok_or_else(|| {
+                format!(
+                    "{} has too many figures",
+                    if white { "White" } else { "Black" }
+                )
+            })?;
+        positions[index] = position;
+    }
+
+    if positions[0].0 == 255 {
+        return Err(format!(
+            "{} has no king",
+            if white { "White" } else { "Black" }
+        ));
+    }
+
+    Ok(positions)
+}
+
+/// Returns the indices of a blank figure in [`Positions`].
+fn get_indices(blank_figure: figures::Figure) -> std::ops::Range<usize> {
+    match blank_figure {
+        figures::KING => 0..1,
+        figures::QUEEN => 1..2,
+        figures::KNIGHT => 2..4,
+        figures::BISHOP => 4..6,
+        figures::ROOK => 6..8,
+        _ => 8..16,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine, pgn};
+
+    #[test]
+    fn boards_survive_a_round_trip() {
+        for fen in [
+            INITIAL_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 12 40",
+            "r3k3/8/8/8/4Pp2/8/8/4K2R b Kq e3 0 1",
+        ] {
+            let game = from_fen(fen).unwrap();
+            assert_eq!(to_fen(&game), fen);
+
+            let loaded = from_fen(&to_fen(&game)).unwrap();
+            assert_eq!(loaded.board, game.board);
+            assert_eq!(loaded.black_figures, game.black_figures);
+            assert_eq!(loaded.white_figures, game.white_figures);
+            assert_eq!(loaded.white, game.white);
+            assert_eq!(loaded.fullmove, game.fullmove);
+        }
+    }
+
+    #[test]
+    fn impossible_castling_rights_are_dropped() {
+        let game = from_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1").unwrap();
+        assert_eq!(game.board.castling, [0, 0]);
+        assert!(engine::generate_legal_moves(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            true
+        )
+        .iter()
+        .all(|r#move| r#move.flags & engine::moves::CASTLING == 0));
+
+        let game = from_fen("r3k3/8/8/8/8/8/8/R3K3 w KQkq - 0 1").unwrap();
+        assert_eq!(game.board.castling, [1, 1]);
+        assert_eq!(to_fen(&game), "r3k3/8/8/8/8/8/8/R3K3 w Qq - 0 1");
+    }
+
+    #[test]
+    fn malformed_rows_are_rejected() {
+        for fen in [
+            "99999999999999999999999999999/8/8/8/8/8/8/4K2k w - - 0 1",
+            "44k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k2/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3p w - - 0 1",
+        ] {
+            assert!(from_fen(fen).is_err(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn boards_from_fen_and_from_moves_have_the_same_key() {
+        for (fen, moves) in [
+            // No pawn can catch en passant, so the position is not remembered.
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                "1. e4",
+            ),
+            (
+                "rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+                "1. e4 a6 2. e5 d5",
+            ),
+        ] {
+            let loaded = from_fen(fen).unwrap();
+            let (_, played) = pgn::from_pgn(moves).unwrap();
+            assert_eq!(loaded.board.hash, played.board.hash, "{}", fen);
+            assert_eq!(loaded.board, played.board, "{}", fen);
+        }
+    }
+}
@@ -1,92 +1,149 @@
+pub mod bitboard;
+pub mod fen;
 pub mod figures;
+pub mod moves;
+pub mod perft;
+pub mod zobrist;
 
-pub type Board = [u8; 66];
-
-/// The last two u8 (index 64 and 65) contain if castling is still possible.
-/// First digit (64): 0 if no castling is allowed, 1 if left castling is allowed, 2 if right castling is allowed and 3 if both castling are allowed for white.
-/// Second digit (65): 0 if no castling is allowed, 1 if left castling is allowed, 2 if right castling is allowed and 3 if both castling are allowed for black.
-pub static INITIAL_BOARD: Board = [
-    figures::WHITE_ROOK,
-    figures::WHITE_KNIGHT,
-    figures::WHITE_BISHOP,
-    figures::WHITE_QUEEN,
-    figures::WHITE_KING,
-    figures::WHITE_BISHOP,
-    figures::WHITE_KNIGHT,
-    figures::WHITE_ROOK,
-    figures::WHITE_PAWN,
-    figures::WHITE_PAWN,
-    figures::WHITE_PAWN,
-    figures::WHITE_PAWN,
-    figures::WHITE_PAWN,
-    figures::WHITE_PAWN,
-    figures::WHITE_PAWN,
-    figures::WHITE_PAWN,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    figures::BLACK_PAWN,
-    figures::BLACK_PAWN,
-    figures::BLACK_PAWN,
-    figures::BLACK_PAWN,
-    figures::BLACK_PAWN,
-    figures::BLACK_PAWN,
-    figures::BLACK_PAWN,
-    figures::BLACK_PAWN,
-    figures::BLACK_ROOK,
-    figures::BLACK_KNIGHT,
-    figures::BLACK_BISHOP,
-    figures::BLACK_QUEEN,
-    figures::BLACK_KING,
-    figures::BLACK_BISHOP,
-    figures::BLACK_KNIGHT,
-    figures::BLACK_ROOK,
-    3,
-    3,
-];
+use bitboard::Bitboard;
+pub use moves::{Move, MoveList};
+
+/// The board: the figures on it, stored twice (for every position and as a [`Bitboard`] for every figure),
+/// and everything else that is needed to know which moves are possible.
+///
+/// The figures must only be changed with [`set_figure`], so that both stay the same.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Board {
+    /// Index `8 * (y - 1) + (x - 1)` contains the figure at the position `(x, y)`, or `0` if it is empty.
+    pub squares: [figures::Figure; 64],
+    /// The positions of every figure, indexed by the figure.
+    pub figures: [Bitboard; 20],
+    /// The positions of all white (index 0) and all black (index 1) figures.
+    pub colors: [Bitboard; 2],
+    /// If castling is still possible for white (index 0) and black (index 1):
+    /// 0 if no castling is allowed, 1 if left castling is allowed, 2 if right castling is allowed and 3 if both castling are allowed.
+    pub castling: [u8; 2],
+    /// The en passant target square: the index (`8 * (y - 1) + (x - 1)`) of the square a pawn skipped with its double step in the last move,
+    /// or 0 if no en passant capture is possible.
+    pub en_passant: u8,
+    /// The number of moves since the last pawn move or catch (for the fifty-move rule).
+    pub halfmove_clock: u8,
+    /// The [`zobrist`] key of the board. It covers everything except the halfmove clock.
+    pub hash: u64,
+}
+
+/// Returns a board with the given figures (see [`Board::squares`]) and castling rights and white to move.
+pub const fn new_board(squares: [figures::Figure; 64], castling: [u8; 2]) -> Board {
+    let mut board = Board {
+        squares,
+        figures: [0; 20],
+        colors: [0; 2],
+        castling,
+        en_passant: 0,
+        halfmove_clock: 0,
+        hash: 0,
+    };
+
+    let mut square = 0;
+    while square < 64 {
+        let figure = squares[square];
+        if figure != 0 {
+            board.figures[figure as usize] |= 1 << square;
+            board.colors[(figure / 10) as usize] |= 1 << square;
+        }
+        square += 1;
+    }
+    board.hash = zobrist::get_hash(&board, true);
+
+    board
+}
+
+pub static INITIAL_BOARD: Board = new_board(
+    [
+        figures::WHITE_ROOK,
+        figures::WHITE_KNIGHT,
+        figures::WHITE_BISHOP,
+        figures::WHITE_QUEEN,
+        figures::WHITE_KING,
+        figures::WHITE_BISHOP,
+        figures::WHITE_KNIGHT,
+        figures::WHITE_ROOK,
+        figures::WHITE_PAWN,
+        figures::WHITE_PAWN,
+        figures::WHITE_PAWN,
+        figures::WHITE_PAWN,
+        figures::WHITE_PAWN,
+        figures::WHITE_PAWN,
+        figures::WHITE_PAWN,
+        figures::WHITE_PAWN,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        figures::BLACK_PAWN,
+        figures::BLACK_PAWN,
+        figures::BLACK_PAWN,
+        figures::BLACK_PAWN,
+        figures::BLACK_PAWN,
+        figures::BLACK_PAWN,
+        figures::BLACK_PAWN,
+        figures::BLACK_PAWN,
+        figures::BLACK_ROOK,
+        figures::BLACK_KNIGHT,
+        figures::BLACK_BISHOP,
+        figures::BLACK_QUEEN,
+        figures::BLACK_KING,
+        figures::BLACK_BISHOP,
+        figures::BLACK_KNIGHT,
+        figures::BLACK_ROOK,
+    ],
+    [3, 3],
+);
 
 /// **Type Position**
 ///
-/// ```
+/// ```text
 /// The Figure
 ///  |       x   y
 ///  |       |   |
 /// (Figure, u8, u8)
 /// ```
 ///
-/// [`Figure`] will be `255` when it is caught.
+/// [`figures::Figure`] will be `255` when it is caught.
 pub type Positions = [Position; 16];
 pub type Position = (figures::Figure, u8, u8);
 
+/// The positions of the white figures on the [`INITIAL_BOARD`].
+///
+/// The index of a figure never changes: the king has index 0, the queen 1, the knights 2 and 3, the bishops 4 and 5, the rooks 6 and 7 and the pawns 8 to 15.
 pub static POSITIONS_WHITE: Positions = [
     (figures::WHITE_KING, 5, 1),
     (figures::WHITE_QUEEN, 4, 1),
@@ -106,6 +163,7 @@ pub static POSITIONS_WHITE: Positions = [
     (figures::WHITE_PAWN, 8, 2),
 ];
 
+/// The positions of the black figures on the [`INITIAL_BOARD`], see [`POSITIONS_WHITE`].
 pub static POSITIONS_BLACK: Positions = [
     (figures::BLACK_KING, 5, 8),
     (figures::BLACK_QUEEN, 4, 8),
@@ -125,356 +183,511 @@ pub static POSITIONS_BLACK: Positions = [
     (figures::BLACK_PAWN, 8, 7),
 ];
 
-/*
-pub static INITIAL_BOARD: Board = [
-    0,
-    0,
-    0,
-    0,
-    figures::WHITE_KING,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    0,
-    figures::BLACK_QUEEN,
-    figures::BLACK_KING,
-    0,
-    0,
-    0,
-    0,
-    0,
-];
+/// Everything needed to continue a game from a board.
+pub struct Game {
+    pub board: Board,
+    pub black_figures: Positions,
+    pub white_figures: Positions,
+    /// `true` if white has to move next.
+    pub white: bool,
+    /// The number of the current move. It starts at 1 and is incremented after each move of black.
+    pub fullmove: u16,
+    /// All boards of the game, the last one being the current board.
+    pub history: Vec<Board>,
+    /// The FEN of the board the game started with.
+    pub start_fen: String,
+    /// All moves made since the game started.
+    pub moves: Vec<Move>,
+}
 
-/// **Type Position**
-///
-/// ```
-/// The Figure
-///  |       x   y
-///  |       |   |
-/// (Figure, u8, u8)
-/// ```
-///
-/// [`Figure`] will be `255` when it is caught.
-pub type Positions = [Position; 16];
-pub type Position = (figures::Figure, u8, u8);
+/// Returns a game with the initial board and white to move.
+pub fn new_game() -> Game {
+    Game {
+        board: INITIAL_BOARD,
+        black_figures: POSITIONS_BLACK,
+        white_figures: POSITIONS_WHITE,
+        white: true,
+        fullmove: 1,
+        history: vec![INITIAL_BOARD],
+        start_fen: fen::INITIAL_FEN.to_string(),
+        moves: Vec::new(),
+    }
+}
 
-pub static POSITIONS_WHITE: Positions = [
-    (figures::WHITE_KING, 5, 1),
-    (255, 4, 1),
-    (255, 2, 1),
-    (255, 7, 1),
-    (255, 3, 1),
-    (255, 6, 1),
-    (255, 1, 1),
-    (255, 8, 1),
-    (255, 1, 2),
-    (255, 2, 2),
-    (255, 3, 2),
-    (255, 4, 2),
-    (255, 5, 2),
-    (255, 6, 2),
-    (255, 7, 2),
-    (255, 8, 2),
-];
+/// Continues `game` with `move`, which was made by the player who had to move and resulted in `value`.
+pub fn apply_turn(game: &mut Game, value: (Board, Positions, Positions), r#move: Move) {
+    game.board = value.0;
+    game.black_figures = value.1;
+    game.white_figures = value.2;
+    game.history.push(game.board);
+    game.moves.push(r#move);
 
-pub static POSITIONS_BLACK: Positions = [
-    (figures::BLACK_KING, 5, 8),
-    (figures::BLACK_QUEEN, 4, 8),
-    (255, 2, 8),
-    (255, 7, 8),
-    (255, 3, 8),
-    (255, 6, 8),
-    (255, 1, 8),
-    (255, 8, 8),
-    (255, 1, 7),
-    (255, 2, 7),
-    (255, 3, 7),
-    (255, 4, 7),
-    (255, 5, 7),
-    (255, 6, 7),
-    (255, 7, 7),
-    (255, 8, 7),
-];
+    if !game.white {
+        game.fullmove += 1;
+    }
+    game.white = !game.white;
+}
+
+/// Returns `game` as it was after the first `ply` moves.
+pub fn get_game_at(game: &Game, ply: usize) -> Game {
+    // The start position is valid, because the game was started from it.
+    let mut result =
+        fen::from_fen(&game.start_fen).expect("The start position of the game is invalid!");
+
+    for &r#move in game.moves.iter().take(ply) {
+        let value = move_figure(
+            result.board,
+            result.black_figures,
+            result.white_figures,
+            r#move,
+            result.white,
+        );
+        apply_turn(&mut result, value, r#move);
+    }
 
+    result
+}
 
- */
+/// The state of a game after a move.
 pub enum GameState {
     Normal((Board, Positions, Positions)),
     CheckMate((Board, Positions, Positions)),
+    Draw((Board, Positions, Positions), DrawReason),
+}
+
+/// The reason why a game ended in a draw.
+pub enum DrawReason {
+    /// The player who has to move has no valid move, but his king is not in check.
+    Stalemate,
+    /// The same board occurred for the third time with the same player to move.
+    Repetition,
+    /// There was no pawn move and no catch in the last fifty moves of each player.
+    FiftyMoves,
+    /// No player has enough figures left to checkmate the other one.
+    InsufficientMaterial,
 }
 
+/// Returns the figure at the given position, or `0` if the position is empty.
 pub fn get_figure(board: Board, x: u8, y: u8) -> figures::Figure {
-    board[(8 * (y - 1) + (x - 1)) as usize]
+    board.squares[(8 * (y - 1) + (x - 1)) as usize]
 }
 
+/// Returns the board with the figure put at the given position.
 pub fn set_figure(mut board: Board, figure: figures::Figure, x: u8, y: u8) -> Board {
-    board[(8 * (y - 1) + (x - 1)) as usize] = figure;
+    put_figure(&mut board, figure, 8 * (y - 1) + (x - 1));
     board
 }
 
-pub fn get_valid_moves(
-    board: Board,
-    figure: figures::Figure,
-    x: u8,
-    y: u8,
-) -> figures::MovementSet {
-    let mut moves = figures::get_relative_moves(figure, y);
-    for i in 0..moves.len() {
-        let mut subset = moves[i];
-        if subset[0].0 > 8 {
-            break;
-        }
+/// Puts the figure at the position with the given index (`8 * (y - 1) + (x - 1)`) and updates the bitboards.
+fn put_figure(board: &mut Board, figure: figures::Figure, square: u8) {
+    let square = square as usize;
 
-        let mut leave = false;
-        for i in 0..subset.len() {
-            let mut position = subset[i];
+    let previous = board.squares[square];
+    if previous != 0 {
+        board.figures[previous as usize] &= !(1 << square);
+        board.colors[(previous / 10) as usize] &= !(1 << square);
+        board.hash ^= zobrist::FIGURES[previous as usize][square];
+    }
 
-            // Make absolute
-            position.0 += x as i8;
-            position.1 += y as i8;
+    board.squares[square] = figure;
+    if figure != 0 {
+        board.figures[figure as usize] |= 1 << square;
+        board.colors[(figure / 10) as usize] |= 1 << square;
+        board.hash ^= zobrist::FIGURES[figure as usize][square];
+    }
+}
 
-            if leave || position.0 > 8 || position.1 > 8 || position.0 < 1 || position.1 < 1 {
-                position.0 = 127;
-                position.1 = 127;
-                subset[i] = position;
-                break;
-            }
+/// Returns all positions the figure at the given position can move to, without checking if the own king would be in check after the move.
+pub fn get_valid_moves(board: Board, figure: figures::Figure, x: u8, y: u8) -> Bitboard {
+    let square = 8 * (y - 1) + (x - 1);
+    let color = (figure / 10) as usize;
+    let occupied = board.colors[0] | board.colors[1];
 
-            let figure_at_move = get_figure(board, position.0 as u8, position.1 as u8);
+    let moves = match figures::colored_figure_to_blank_figure(figure) {
+        figures::KING => {
+            let mut moves = bitboard::KING_ATTACKS[square as usize];
 
-            // Check if the figure at the position is the opposite color (and can therefore be caught)
-            leave = figure_at_move != 0;
+            // Special king/rook rule: Castling. See https://schach.de/de/page/schachregeln-die-rochade
+            for to_x in [3, 7] {
+                if is_castling_allowed(board, figure, x, y, to_x) {
+                    moves |= bitboard::bit(to_x, y);
+                }
+            }
 
-            // Check if the figure at the position is the same color
-            if leave && figure_at_move / 10 == figure / 10 {
-                position.0 = 127;
-                position.1 = 127;
-                subset[i] = position;
-                break;
+            moves
+        }
+        figures::QUEEN => bitboard::queen_attacks(square, occupied),
+        figures::KNIGHT => bitboard::KNIGHT_ATTACKS[square as usize],
+        figures::BISHOP => bitboard::bishop_attacks(square, occupied),
+        figures::ROOK => bitboard::rook_attacks(square, occupied),
+        _ => {
+            // Special pawn rule: It can only move diagonally if it can catch a figure there (or catch a pawn en passant) and it cannot move forward if would catch a figure there.
+            let mut catchable = board.colors[1 - color];
+            if board.en_passant != 0 {
+                catchable |= 1 << board.en_passant;
             }
+            let mut moves = bitboard::PAWN_ATTACKS[color][square as usize] & catchable;
 
-            // Special pawn rule: It can only move diagonally if it can catch a figure there and it cannot move forward if would catch a figure there.
-            if (figure == figures::WHITE_PAWN || figure == figures::BLACK_PAWN)
-                && ((position.0 as u8 != x && !leave) || (position.0 as u8 == x && leave))
-            {
-                position.0 = 127;
-                position.1 = 127;
-                subset[i] = position;
-                break;
-            // Special king/rook rule: Castling. See https://schach.de/de/page/schachregeln-die-rochade
-            } else if figure == figures::WHITE_KING || figure == figures::BLACK_KING {
-                if position.0 == x as i8 - 2 {
-                    if !((board[(64 + figure / 10) as usize] == 1
-                        || board[(64 + figure / 10) as usize] == 3)
-                        && get_figure(board, (position.0 - 1) as u8, position.1 as u8) == 0)
-                    {
-                        position.0 = 127;
-                        position.1 = 127;
-                        subset[i] = position;
-                        break;
-                    }
-                } else if position.0 == x as i8 + 2 {
-                    if board[(64 + figure / 10) as usize] < 2 {
-                        position.0 = 127;
-                        position.1 = 127;
-                        subset[i] = position;
-                        break;
-                    }
+            let (forward_y, start_y) = if color == 0 { (y + 1, 2) } else { (y - 1, 7) };
+            if get_figure(board, x, forward_y) == 0 {
+                moves |= bitboard::bit(x, forward_y);
+
+                let double_y = if color == 0 { 4 } else { 5 };
+                if y == start_y && get_figure(board, x, double_y) == 0 {
+                    moves |= bitboard::bit(x, double_y);
                 }
             }
 
-            subset[i] = position;
+            moves
         }
-        moves[i] = subset;
+    };
+
+    moves & !board.colors[color]
+}
+
+/// Checks if the king can castle to the given x-coordinate (3 for left castling, 7 for right castling):
+/// * The king and the rook have not moved yet (the castling right at index 64/65 is set).
+/// * The rook still stands in the corner.
+/// * All positions between the king and the rook are empty.
+/// * The king is not in check and does not move over or to a position that is attacked.
+fn is_castling_allowed(board: Board, king: figures::Figure, x: u8, y: u8, to_x: u8) -> bool {
+    let white = king / 10 == 0;
+    let (right, rook_x, between): (u8, u8, &[u8]) = if to_x < x {
+        (1, 1, &[2, 3, 4])
+    } else {
+        (2, 8, &[6, 7])
+    };
+
+    x == 5
+        && board.castling[(king / 10) as usize] & right != 0
+        && get_figure(board, rook_x, y) == king - figures::KING + figures::ROOK
+        && between
+            .iter()
+            .all(|&between_x| get_figure(board, between_x, y) == 0)
+        && !is_position_attacked(board, x, y, !white)
+        && !is_position_attacked(board, (x + to_x) / 2, y, !white)
+        && !is_position_attacked(board, to_x, y, !white)
+}
+
+/// Checks if a figure of the given player (`white`) could catch a figure at the given position.
+pub fn is_position_attacked(board: Board, x: u8, y: u8, white: bool) -> bool {
+    let square = 8 * (y - 1) + (x - 1);
+    let color = if white { 0 } else { 10 };
+    let occupied = board.colors[0] | board.colors[1];
+    let attackers = |figure: figures::Figure| board.figures[(figure + color) as usize];
+
+    // Pawns catch diagonally forward, so the attacking pawn stands where a pawn of the other player on the position would catch.
+    bitboard::PAWN_ATTACKS[if white { 1 } else { 0 }][square as usize] & attackers(figures::PAWN)
+        != 0
+        || bitboard::KNIGHT_ATTACKS[square as usize] & attackers(figures::KNIGHT) != 0
+        || bitboard::KING_ATTACKS[square as usize] & attackers(figures::KING) != 0
+        || bitboard::bishop_attacks(square, occupied)
+            & (attackers(figures::BISHOP) | attackers(figures::QUEEN))
+            != 0
+        || bitboard::rook_attacks(square, occupied)
+            & (attackers(figures::ROOK) | attackers(figures::QUEEN))
+            != 0
+}
+
+/// Checks if a pawn moving to the given position catches a pawn en passant.
+/// See https://schach.de/de/page/schachregeln-en-passant
+pub fn is_en_passant(board: Board, figure: figures::Figure, x: u8, y: u8) -> bool {
+    board.en_passant != 0
+        && board.en_passant == 8 * (y - 1) + (x - 1)
+        && ((figure == figures::WHITE_PAWN && y == 6) || (figure == figures::BLACK_PAWN && y == 3))
+}
+
+/// Checks if a pawn moving to the given row gets promoted.
+/// See https://schach.de/de/page/schachregeln-die-umwandlung
+pub fn is_promotion(figure: figures::Figure, to_y: u8) -> bool {
+    (figure == figures::WHITE_PAWN && to_y == 8) || (figure == figures::BLACK_PAWN && to_y == 1)
+}
+
+/// Returns the blank figures a move of the given figure to the given row has to be made with:
+/// All [`figures::PROMOTION_FIGURES`] if the move is a promotion, otherwise only `0` (no promotion).
+pub fn get_promotions(figure: figures::Figure, to_y: u8) -> &'static [figures::Figure] {
+    if is_promotion(figure, to_y) {
+        &figures::PROMOTION_FIGURES
+    } else {
+        &[0]
     }
-    moves
 }
 
+/// Makes the move of the player (`white`) and returns the resulting board and positions.
+///
+/// This copies the board and the positions, the search uses [`make_move`] and [`unmake_move`] instead.
 pub fn move_figure(
     mut board: Board,
     mut black_figures: Positions,
     mut white_figures: Positions,
-    mut figure: figures::Figure,
-    from_x: u8,
-    from_y: u8,
-    to_x: u8,
-    to_y: u8,
+    r#move: Move,
     white: bool,
 ) -> (Board, Positions, Positions) {
-    board = set_figure(board, 0, from_x, from_y);
+    make_move(
+        &mut board,
+        &mut black_figures,
+        &mut white_figures,
+        r#move,
+        white,
+    );
+    (board, black_figures, white_figures)
+}
 
-    let caught_figure = get_figure(board, to_x, to_y);
+/// Everything [`unmake_move`] needs to take back a move, that cannot be read from the move itself.
+#[derive(Clone, Copy, Debug)]
+pub struct Undo {
+    /// The index of the moved figure in the [`Positions`] of the player.
+    pub index: usize,
+    /// The index of the caught figure in the [`Positions`] of the opponent and its position before it was caught.
+    pub caught: Option<(usize, Position)>,
+    pub castling: [u8; 2],
+    pub en_passant: u8,
+    pub halfmove_clock: u8,
+    pub hash: u64,
+}
 
-    // Check if the move catches a figure
-    if caught_figure != 0 {
-        // Mark that figure as caught
-        if white {
-            for i in (caught_figure - 10 - 1) as usize..black_figures.len() {
-                let mut figure = black_figures[i as usize];
-                if figure.0 != 255 && figure.1 == to_x && figure.2 == to_y {
-                    figure.0 = 255;
-                    black_figures[i] = figure;
-                }
-            }
+/// Makes the move of the player (`white`) on the board and the positions in place.
+///
+/// Returns the [`Undo`] record that [`unmake_move`] needs to restore the board and the positions.
+pub fn make_move(
+    board: &mut Board,
+    black_figures: &mut Positions,
+    white_figures: &mut Positions,
+    r#move: Move,
+    white: bool,
+) -> Undo {
+    let (from_x, from_y) = bitboard::to_position(r#move.from);
+    let (to_x, to_y) = bitboard::to_position(r#move.to);
+    let figure = board.squares[r#move.from as usize];
+    let blank_figure = figures::colored_figure_to_blank_figure(figure);
+    let (own_figures, opponent_figures) = if white {
+        (white_figures, black_figures)
+    } else {
+        (black_figures, white_figures)
+    };
+
+    let mut undo = Undo {
+        index: 0,
+        caught: None,
+        castling: board.castling,
+        en_passant: board.en_passant,
+        halfmove_clock: board.halfmove_clock,
+        hash: board.hash,
+    };
+
+    // Special rule en passant: The caught pawn is not on the target position, but next to the start position.
+    if r#move.flags & moves::CAPTURE != 0 {
+        let (caught_x, caught_y) = if r#move.flags & moves::EN_PASSANT != 0 {
+            (to_x, from_y)
         } else {
-            for i in (caught_figure - 1) as usize..white_figures.len() {
-                let mut figure = white_figures[i];
-                if figure.0 != 255 && figure.1 == to_x && figure.2 == to_y {
-                    figure.0 = 255;
-                    white_figures[i] = figure;
-                }
-            }
+            (to_x, to_y)
+        };
+        let caught_square = bitboard::to_square(caught_x, caught_y);
+        let caught_figure = board.squares[caught_square as usize];
+        put_figure(board, 0, caught_square);
+
+        // Mark that figure as caught
+        let start = (figures::colored_figure_to_blank_figure(caught_figure) - 1) as usize;
+        if let Some(index) = (start..16).find(|&index| {
+            let position = opponent_figures[index];
+            position.0 != 255 && position.1 == caught_x && position.2 == caught_y
+        }) {
+            undo.caught = Some((index, opponent_figures[index]));
+            opponent_figures[index].0 = 255;
         }
     }
 
-    // Update position of figure
-    if white {
-        for i in (figure - 1) as usize..white_figures.len() {
-            let mut figure = white_figures[i];
-            if figure.1 == from_x && figure.2 == from_y {
-                if figure.0 == figures::WHITE_PAWN && to_y == 8 {
-                    figure.0 = figures::WHITE_QUEEN;
-                }
-                figure.1 = to_x;
-                figure.2 = to_y;
-            }
+    // Update position of figure. A figure only stands at or behind the first index of its kind (see `fen::get_positions`).
+    undo.index = ((blank_figure - 1) as usize..16)
+        .find(|&index| {
+            let position = own_figures[index];
+            position.0 != 255 && position.1 == from_x && position.2 == from_y
+        })
+        .expect("The moved figure is not in the positions!");
 
-            white_figures[i] = figure;
-        }
+    let moved_figure = if r#move.promotion != 0 {
+        figure - figures::PAWN + r#move.promotion
     } else {
-        for i in (figure - 10 - 1) as usize..black_figures.len() {
-            let mut figure = black_figures[i];
-            if figure.1 == from_x && figure.2 == from_y {
-                if figure.0 == figures::BLACK_PAWN && to_y == 1 {
-                    figure.0 = figures::BLACK_QUEEN;
-                }
-                figure.1 = to_x;
-                figure.2 = to_y;
-            }
+        figure
+    };
+    own_figures[undo.index] = (moved_figure, to_x, to_y);
+    put_figure(board, 0, r#move.from);
+    put_figure(board, moved_figure, r#move.to);
+
+    // Special rule castling: The rook jumps over the king.
+    if r#move.flags & moves::CASTLING != 0 {
+        let (rook_from_x, rook_to_x) = get_castling_rook(from_x, to_x);
+        move_rook(board, own_figures, from_y, rook_from_x, rook_to_x);
+    }
 
-            black_figures[i] = figure;
-        }
+    if blank_figure == figures::KING {
+        board.castling[(figure / 10) as usize] = 0;
     }
 
-    // Special rule castling
-    if (figures::colored_figure_to_blank_figure(figure) == figures::KING) && from_x == 5 {
-        if to_x == from_x - 2 {
-            let update = move_figure(
-                board,
-                black_figures,
-                white_figures,
-                get_figure(board, 1, from_y),
-                1,
-                from_y,
-                4,
-                from_y,
-                white,
-            );
-            board = update.0;
-            black_figures = update.1;
-            white_figures = update.2;
-        } else if to_x == from_x + 2 {
-            let update = move_figure(
-                board,
-                black_figures,
-                white_figures,
-                get_figure(board, 8, from_y),
-                8,
-                from_y,
-                6,
-                from_y,
-                white,
-            );
-            board = update.0;
-            black_figures = update.1;
-            white_figures = update.2;
-        }
-        board[(64 + figure / 10) as usize] = 0;
-    } else if figures::colored_figure_to_blank_figure(figure) == figures::ROOK {
-        if (board[(64 + figure / 10) as usize] == 1 || board[(64 + figure / 10) as usize] == 3)
-            && from_x == 1
-        {
-            board[(64 + figure / 10) as usize] -= 1
-        } else if board[(64 + figure / 10) as usize] >= 2 && from_x == 8 {
-            board[(64 + figure / 10) as usize] -= 2
+    // Castling with a rook is not possible anymore, once it moved away from its corner or was caught there.
+    for (corner_x, corner_y) in [(from_x, from_y), (to_x, to_y)] {
+        let index = match corner_y {
+            1 => 0,
+            8 => 1,
+            _ => continue,
+        };
+
+        if corner_x == 1 {
+            board.castling[index] &= !1;
+        } else if corner_x == 8 {
+            board.castling[index] &= !2;
         }
     }
 
-    if figure == figures::WHITE_PAWN && to_y == 8 {
-        figure = figures::WHITE_QUEEN;
-    } else if figure == figures::BLACK_PAWN && to_y == 1 {
-        figure = figures::BLACK_PAWN;
+    // Remember the skipped position if a pawn made a double step next to a pawn of the opponent, which could catch it en passant in the next move.
+    let opponent_pawn = if white {
+        figures::BLACK_PAWN
+    } else {
+        figures::WHITE_PAWN
+    };
+    board.en_passant = if blank_figure == figures::PAWN
+        && from_y.abs_diff(to_y) == 2
+        && ((to_x > 1 && get_figure(*board, to_x - 1, to_y) == opponent_pawn)
+            || (to_x < 8 && get_figure(*board, to_x + 1, to_y) == opponent_pawn))
+    {
+        bitboard::to_square(to_x, (from_y + to_y) / 2)
+    } else {
+        0
+    };
+
+    // The figures were already updated in the key, the castling rights, the en passant position and the player to move are missing.
+    for color in 0..2 {
+        board.hash ^= zobrist::CASTLING[color][undo.castling[color] as usize]
+            ^ zobrist::CASTLING[color][board.castling[color] as usize];
     }
+    if undo.en_passant != 0 {
+        board.hash ^= zobrist::EN_PASSANT[(undo.en_passant % 8) as usize];
+    }
+    if board.en_passant != 0 {
+        board.hash ^= zobrist::EN_PASSANT[(board.en_passant % 8) as usize];
+    }
+    board.hash ^= zobrist::BLACK_TO_MOVE;
 
-    board = set_figure(board, figure, to_x, to_y);
+    // The halfmove clock is reset by every pawn move and every catch.
+    board.halfmove_clock = if blank_figure == figures::PAWN || r#move.flags & moves::CAPTURE != 0 {
+        0
+    } else {
+        board.halfmove_clock.saturating_add(1)
+    };
 
-    (board, black_figures, white_figures)
+    undo
 }
 
-pub fn contains_position(movement_set: figures::MovementSet, position: (u8, u8)) -> bool {
-    for subset in movement_set {
-        for r#move in subset {
-            if r#move.0 > 8 {
-                break;
-            }
-            if r#move.0 == position.0 as i8 && r#move.1 == position.1 as i8 {
-                return true;
-            }
+/// Takes back a move made with [`make_move`], `undo` being the record it returned.
+pub fn unmake_move(
+    board: &mut Board,
+    black_figures: &mut Positions,
+    white_figures: &mut Positions,
+    r#move: Move,
+    undo: Undo,
+    white: bool,
+) {
+    let (from_x, from_y) = bitboard::to_position(r#move.from);
+    let (to_x, _) = bitboard::to_position(r#move.to);
+    let (own_figures, opponent_figures) = if white {
+        (white_figures, black_figures)
+    } else {
+        (black_figures, white_figures)
+    };
+
+    if r#move.flags & moves::CASTLING != 0 {
+        let (rook_from_x, rook_to_x) = get_castling_rook(from_x, to_x);
+        move_rook(board, own_figures, from_y, rook_to_x, rook_from_x);
+    }
+
+    let figure = if r#move.promotion != 0 {
+        if white {
+            figures::WHITE_PAWN
+        } else {
+            figures::BLACK_PAWN
         }
+    } else {
+        board.squares[r#move.to as usize]
+    };
+    own_figures[undo.index] = (figure, from_x, from_y);
+    put_figure(board, 0, r#move.to);
+    put_figure(board, figure, r#move.from);
+
+    if let Some((index, position)) = undo.caught {
+        opponent_figures[index] = position;
+        put_figure(
+            board,
+            position.0,
+            bitboard::to_square(position.1, position.2),
+        );
     }
-    false
+
+    board.castling = undo.castling;
+    board.en_passant = undo.en_passant;
+    board.halfmove_clock = undo.halfmove_clock;
+    board.hash = undo.hash;
+}
+
+/// Passes the move of the player to the opponent without moving a figure (a null move), e.g. for the null move pruning of the search.
+///
+/// The halfmove clock is reset, so that no board before the null move counts as a repetition.
+/// Returns the [`Undo`] record that [`unmake_null_move`] needs to restore the board.
+pub fn make_null_move(board: &mut Board) -> Undo {
+    let undo = Undo {
+        index: 0,
+        caught: None,
+        castling: board.castling,
+        en_passant: board.en_passant,
+        halfmove_clock: board.halfmove_clock,
+        hash: board.hash,
+    };
+
+    if board.en_passant != 0 {
+        board.hash ^= zobrist::EN_PASSANT[(board.en_passant % 8) as usize];
+        board.en_passant = 0;
+    }
+    board.halfmove_clock = 0;
+    board.hash ^= zobrist::BLACK_TO_MOVE;
+
+    undo
+}
+
+/// Takes back a null move made with [`make_null_move`].
+pub fn unmake_null_move(board: &mut Board, undo: Undo) {
+    board.en_passant = undo.en_passant;
+    board.halfmove_clock = undo.halfmove_clock;
+    board.hash = undo.hash;
 }
 
+/// Returns the x-coordinates the rook moves from and to, if the king castles from `from_x` to `to_x`.
+fn get_castling_rook(from_x: u8, to_x: u8) -> (u8, u8) {
+    if to_x < from_x {
+        (1, 4)
+    } else {
+        (8, 6)
+    }
+}
+
+/// Moves the rook in row `y` from `from_x` to `to_x` on the board and in the positions.
+fn move_rook(board: &mut Board, own_figures: &mut Positions, y: u8, from_x: u8, to_x: u8) {
+    let rook = get_figure(*board, from_x, y);
+    put_figure(board, 0, 8 * (y - 1) + (from_x - 1));
+    put_figure(board, rook, 8 * (y - 1) + (to_x - 1));
+
+    if let Some(position) = own_figures
+        .iter_mut()
+        .find(|position| position.0 != 255 && position.1 == from_x && position.2 == y)
+    {
+        position.1 = to_x;
+    }
+}
+
+/// Checks if the position is one of the moves in the bitboard.
+pub fn contains_position(moves: Bitboard, position: (u8, u8)) -> bool {
+    moves & bitboard::bit(position.0, position.1) != 0
+}
+
+/// Checks if the king of the player (`white`) can not be caught in the next move.
 pub fn is_board_valid(
     board: Board,
     black_figures: Positions,
@@ -489,31 +702,51 @@ pub fn is_board_valid(
         }
     };
 
-    for figure in {
-        if white {
-            black_figures
-        } else {
-            white_figures
-        }
-    } {
-        if figure.0 == 255 {
-            continue;
-        }
-
-        let movement_set = get_valid_moves(board, figure.0, figure.1, figure.2);
-        if contains_position(movement_set, (king.1, king.2)) {
-            return false;
-        }
-    }
-    true
+    !is_position_attacked(board, king.1, king.2, !white)
 }
 
-pub fn is_checkmate(
+/// Checks if the player has at least one move that does not leave his king in check.
+pub fn has_valid_move(
     board: Board,
     black_figures: Positions,
     white_figures: Positions,
     white: bool,
 ) -> bool {
+    !generate_legal_moves(board, black_figures, white_figures, white).is_empty()
+}
+
+/// Returns all moves of the player that do not leave his king in check.
+///
+/// A move of a pawn to the last row is returned once for every figure it can be promoted to.
+pub fn generate_legal_moves(
+    board: Board,
+    black_figures: Positions,
+    white_figures: Positions,
+    white: bool,
+) -> MoveList {
+    generate_moves(board, black_figures, white_figures, white, false)
+}
+
+/// Returns the moves of [`generate_legal_moves`] that catch a figure or promote a pawn.
+pub fn generate_legal_captures(
+    board: Board,
+    black_figures: Positions,
+    white_figures: Positions,
+    white: bool,
+) -> MoveList {
+    generate_moves(board, black_figures, white_figures, white, true)
+}
+
+/// Generates the moves for [`generate_legal_moves`] and, if `captures_only` is `true`, [`generate_legal_captures`].
+fn generate_moves(
+    mut board: Board,
+    mut black_figures: Positions,
+    mut white_figures: Positions,
+    white: bool,
+    captures_only: bool,
+) -> MoveList {
+    let mut legal_moves = MoveList::new();
+
     for figure in {
         if white {
             white_figures
@@ -524,36 +757,135 @@ pub fn is_checkmate(
         if figure.0 == 255 {
             continue;
         }
-        let movement_set = get_valid_moves(board, figure.0, figure.1, figure.2);
-        ////print!("{} at x{}y{}", get_str(figure.0), figure.1, figure.2);
-        for subset in movement_set {
-            for r#move in subset {
-                if r#move.0 > 8 {
-                    break;
-                }
 
-                ////print!(" to x{}y{} | ", r#move.0, r#move.1);
-
-                let board = move_figure(
-                    board,
-                    black_figures,
-                    white_figures,
-                    figure.0,
-                    figure.1,
-                    figure.2,
-                    r#move.0 as u8,
-                    r#move.1 as u8,
+        let from = bitboard::to_square(figure.1, figure.2);
+        let blank_figure = figures::colored_figure_to_blank_figure(figure.0);
+
+        let targets = get_valid_moves(board, figure.0, figure.1, figure.2);
+        for to in bitboard::squares(targets) {
+            let (to_x, to_y) = bitboard::to_position(to);
+
+            let flags = if board.squares[to as usize] != 0 {
+                moves::CAPTURE
+            } else if blank_figure == figures::PAWN
+                && to_x != figure.1
+                && is_en_passant(board, figure.0, to_x, to_y)
+            {
+                moves::CAPTURE | moves::EN_PASSANT
+            } else if blank_figure == figures::KING && to_x.abs_diff(figure.1) == 2 {
+                moves::CASTLING
+            } else {
+                0
+            };
+
+            let promotions = get_promotions(figure.0, to_y);
+            if captures_only && flags & moves::CAPTURE == 0 && promotions[0] == 0 {
+                continue;
+            }
+
+            for &promotion in promotions {
+                let r#move = Move {
+                    from,
+                    to,
+                    promotion,
+                    flags,
+                };
+                let undo = make_move(
+                    &mut board,
+                    &mut black_figures,
+                    &mut white_figures,
+                    r#move,
                     white,
                 );
-
-                if is_board_valid(board.0, board.1, board.2, white) {
-                    ////println!();
-                    return false;
+                if is_board_valid(board, black_figures, white_figures, white) {
+                    legal_moves.push(r#move);
                 }
+                unmake_move(
+                    &mut board,
+                    &mut black_figures,
+                    &mut white_figures,
+                    r#move,
+                    undo,
+                    white,
+                );
+            }
+        }
+    }
+
+    legal_moves
+}
+
+/// Counts how often the board occurred before with the same player to move.
+///
+/// `history` contains all previous boards of the game, the last one being the board before `board`.
+/// Only boards since the last pawn move or catch are compared, because no board before can occur again.
+pub fn count_repetitions(board: Board, history: &[Board]) -> usize {
+    history
+        .iter()
+        .rev()
+        .take(board.halfmove_clock as usize)
+        .skip(1)
+        .step_by(2)
+        // The halfmove clock is not part of the key.
+        .filter(|previous| previous.hash == board.hash)
+        .count()
+}
+
+/// Checks if no player has enough figures left to checkmate the other one:
+/// King against king, king and bishop against king, king and knight against king
+/// or only bishops on squares of the same color besides the kings.
+pub fn is_insufficient_material(black_figures: Positions, white_figures: Positions) -> bool {
+    let mut knights = 0;
+    let mut bishops = 0;
+    let mut bishop_colors = [false; 2];
+
+    for figure in black_figures.iter().chain(white_figures.iter()) {
+        if figure.0 == 255 {
+            continue;
+        }
+
+        match figures::colored_figure_to_blank_figure(figure.0) {
+            figures::KING => {}
+            figures::KNIGHT => knights += 1,
+            figures::BISHOP => {
+                bishops += 1;
+                bishop_colors[((figure.1 + figure.2) % 2) as usize] = true;
             }
+            _ => return false,
         }
-        ////println!()
     }
 
-    true
+    knights + bishops <= 1 || (knights == 0 && !(bishop_colors[0] && bishop_colors[1]))
+}
+
+/// Classifies the board from the view of the player who has to move next (`white`).
+///
+/// `history` contains all previous boards of the game, the last one being the board before `board`.
+///
+/// Returns [`GameState::CheckMate`] if the player is checkmate (the other player has won),
+/// [`GameState::Draw`] if the game ended in a draw and [`GameState::Normal`] otherwise.
+pub fn get_game_state(
+    board: Board,
+    black_figures: Positions,
+    white_figures: Positions,
+    white: bool,
+    history: &[Board],
+) -> GameState {
+    let figures = (board, black_figures, white_figures);
+
+    if !has_valid_move(board, black_figures, white_figures, white) {
+        if is_board_valid(board, black_figures, white_figures, white) {
+            GameState::Draw(figures, DrawReason::Stalemate)
+        } else {
+            GameState::CheckMate(figures)
+        }
+    } else if is_insufficient_material(black_figures, white_figures) {
+        GameState::Draw(figures, DrawReason::InsufficientMaterial)
+    } else if board.halfmove_clock >= 100 {
+        GameState::Draw(figures, DrawReason::FiftyMoves)
+    } else if count_repetitions(board, history) >= 2 {
+        GameState::Draw(figures, DrawReason::Repetition)
+    } else {
+        GameState::Normal(figures)
+    }
 }
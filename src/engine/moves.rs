@@ -0,0 +1,75 @@
+use super::figures;
+
+/// The move catches a figure.
+pub const CAPTURE: u8 = 1;
+/// The move is a castling, the rook is moved together with the king.
+pub const CASTLING: u8 = 2;
+/// The move catches a pawn en passant. It is always a [`CAPTURE`] too.
+pub const EN_PASSANT: u8 = 4;
+
+/// A move, stored in four bytes.
+///
+/// The positions are stored as their index `8 * (y - 1) + (x - 1)`, see [`super::bitboard::to_position`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Move {
+    /// The position the figure moves from.
+    pub from: u8,
+    /// The position the figure moves to.
+    pub to: u8,
+    /// The blank figure a pawn is promoted to, or `0` if the move is no promotion.
+    pub promotion: figures::Figure,
+    /// Any of [`CAPTURE`], [`CASTLING`] and [`EN_PASSANT`].
+    pub flags: u8,
+}
+
+/// A move that is never made. It is returned instead of a move if the player has none.
+pub const NULL_MOVE: Move = Move {
+    from: 0,
+    to: 0,
+    promotion: 0,
+    flags: 0,
+};
+
+/// No board has more than 218 legal moves, see https://www.chessprogramming.org/Chess_Position
+const MAX_MOVES: usize = 256;
+
+/// A list of moves, which is stored on the stack instead of the heap.
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub const fn new() -> MoveList {
+        MoveList {
+            moves: [NULL_MOVE; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, r#move: Move) {
+        self.moves[self.len] = r#move;
+        self.len += 1;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        MoveList::new()
+    }
+}
+
+impl std::ops::Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl std::ops::DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
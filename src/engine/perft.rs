@@ -0,0 +1,151 @@
+use super::{Board, Move, Positions};
+
+/// Counts all boards that can be reached with exactly `depth` legal moves (performance test).
+///
+/// The numbers can be compared with known numbers of reference boards to find bugs in the move generation,
+/// see https://www.chessprogramming.org/Perft_Results
+pub fn perft(
+    mut board: Board,
+    mut black_figures: Positions,
+    mut white_figures: Positions,
+    white: bool,
+    depth: u8,
+) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = super::generate_legal_moves(board, black_figures, white_figures, white);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for &r#move in moves.iter() {
+        let undo = super::make_move(
+            &mut board,
+            &mut black_figures,
+            &mut white_figures,
+            r#move,
+            white,
+        );
+        nodes += perft(board, black_figures, white_figures, !white, depth - 1);
+        super::unmake_move(
+            &mut board,
+            &mut black_figures,
+            &mut white_figures,
+            r#move,
+            undo,
+            white,
+        );
+    }
+    nodes
+}
+
+/// Like [`perft`], but returns the number of boards for every legal move separately.
+pub fn perft_divide(
+    board: Board,
+    black_figures: Positions,
+    white_figures: Positions,
+    white: bool,
+    depth: u8,
+) -> Vec<(Move, u64)> {
+    super::generate_legal_moves(board, black_figures, white_figures, white)
+        .iter()
+        .map(|&r#move| {
+            let (board, black_figures, white_figures) =
+                super::move_figure(board, black_figures, white_figures, r#move, white);
+            (
+                r#move,
+                perft(
+                    board,
+                    black_figures,
+                    white_figures,
+                    !white,
+                    depth.max(1) - 1,
+                ),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::fen;
+
+    fn perft_fen(fen: &str, depth: u8) -> u64 {
+        let game = fen::from_fen(fen).unwrap();
+        perft(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            depth,
+        )
+    }
+
+    #[test]
+    fn initial_board() {
+        let fen = fen::INITIAL_FEN;
+        assert_eq!(perft_fen(fen, 1), 20);
+        assert_eq!(perft_fen(fen, 2), 400);
+        assert_eq!(perft_fen(fen, 3), 8902);
+        assert_eq!(perft_fen(fen, 4), 197281);
+    }
+
+    #[test]
+    fn kiwipete() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(perft_fen(fen, 1), 48);
+        assert_eq!(perft_fen(fen, 2), 2039);
+        assert_eq!(perft_fen(fen, 3), 97862);
+    }
+
+    #[test]
+    fn en_passant_and_pins() {
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        assert_eq!(perft_fen(fen, 1), 14);
+        assert_eq!(perft_fen(fen, 2), 191);
+        assert_eq!(perft_fen(fen, 3), 2812);
+        assert_eq!(perft_fen(fen, 4), 43238);
+    }
+
+    #[test]
+    fn promotion_and_castling() {
+        let fen = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+        assert_eq!(perft_fen(fen, 1), 6);
+        assert_eq!(perft_fen(fen, 2), 264);
+        assert_eq!(perft_fen(fen, 3), 9467);
+    }
+
+    #[test]
+    fn promotion_with_check() {
+        let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+        assert_eq!(perft_fen(fen, 1), 44);
+        assert_eq!(perft_fen(fen, 2), 1486);
+        assert_eq!(perft_fen(fen, 3), 62379);
+    }
+
+    #[test]
+    fn middle_game() {
+        let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+        assert_eq!(perft_fen(fen, 1), 46);
+        assert_eq!(perft_fen(fen, 2), 2079);
+        assert_eq!(perft_fen(fen, 3), 89890);
+    }
+
+    #[test]
+    fn divide_sums_up() {
+        let game = fen::from_fen(fen::INITIAL_FEN).unwrap();
+        let divide = perft_divide(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            3,
+        );
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8902);
+    }
+}
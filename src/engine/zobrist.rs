@@ -0,0 +1,157 @@
+//! Zobrist hashing: every board gets a 64 bit key, which is the XOR of random numbers for every figure on its position,
+//! the player to move, the castling rights and the en passant file (see https://www.chessprogramming.org/Zobrist_Hashing).
+//!
+//! The key is stored in [`Board::hash`] and updated with every change of the board, so it never has to be calculated from scratch.
+
+use super::Board;
+
+/// Generates `N` pseudo random numbers with SplitMix64, so that the keys are the same in every build.
+const fn generate_keys<const N: usize>(mut seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+
+    let mut i = 0;
+    while i < N {
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+
+    keys
+}
+
+const fn generate_figure_keys() -> [[u64; 64]; 20] {
+    let keys: [u64; 20 * 64] = generate_keys(1);
+    let mut figure_keys = [[0; 64]; 20];
+
+    let mut figure = 0;
+    while figure < 20 {
+        let mut square = 0;
+        while square < 64 {
+            figure_keys[figure][square] = keys[64 * figure + square];
+            square += 1;
+        }
+        figure += 1;
+    }
+
+    figure_keys
+}
+
+/// The key of every figure on every position, indexed like [`Board::figures`] and [`Board::squares`].
+pub static FIGURES: [[u64; 64]; 20] = generate_figure_keys();
+
+/// The key of the castling rights of white (index 0) and black (index 1), indexed by the value in [`Board::castling`].
+pub static CASTLING: [[u64; 4]; 2] = {
+    let keys: [u64; 8] = generate_keys(2);
+    [
+        [keys[0], keys[1], keys[2], keys[3]],
+        [keys[4], keys[5], keys[6], keys[7]],
+    ]
+};
+
+/// The key of the file (`x - 1`) of the en passant position, if there is one.
+pub static EN_PASSANT: [u64; 8] = generate_keys(3);
+
+/// The key that is added if black has to move.
+pub static BLACK_TO_MOVE: u64 = generate_keys::<1>(4)[0];
+
+/// Calculates the key of the board from scratch, `white` being `true` if white has to move.
+pub const fn get_hash(board: &Board, white: bool) -> u64 {
+    let mut hash = 0;
+
+    let mut square = 0;
+    while square < 64 {
+        let figure = board.squares[square];
+        if figure != 0 {
+            hash ^= FIGURES[figure as usize][square];
+        }
+        square += 1;
+    }
+
+    hash ^= CASTLING[0][board.castling[0] as usize] ^ CASTLING[1][board.castling[1] as usize];
+
+    if board.en_passant != 0 {
+        hash ^= EN_PASSANT[(board.en_passant % 8) as usize];
+    }
+
+    if !white {
+        hash ^= BLACK_TO_MOVE;
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{self, fen, Positions};
+
+    /// Checks that the updated key is the same as the calculated key after every move up to `depth` plies.
+    fn check_keys(
+        mut board: Board,
+        mut black_figures: Positions,
+        mut white_figures: Positions,
+        white: bool,
+        depth: u8,
+    ) {
+        assert_eq!(board.hash, get_hash(&board, white));
+        if depth == 0 {
+            return;
+        }
+
+        for &r#move in
+            engine::generate_legal_moves(board, black_figures, white_figures, white).iter()
+        {
+            let before = board;
+            let undo = engine::make_move(
+                &mut board,
+                &mut black_figures,
+                &mut white_figures,
+                r#move,
+                white,
+            );
+            check_keys(board, black_figures, white_figures, !white, depth - 1);
+            engine::unmake_move(
+                &mut board,
+                &mut black_figures,
+                &mut white_figures,
+                r#move,
+                undo,
+                white,
+            );
+            assert_eq!(board, before);
+        }
+    }
+
+    #[test]
+    fn updated_keys_are_correct() {
+        for fen in [
+            fen::INITIAL_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let game = fen::from_fen(fen).unwrap();
+            check_keys(
+                game.board,
+                game.black_figures,
+                game.white_figures,
+                game.white,
+                3,
+            );
+        }
+    }
+
+    #[test]
+    fn transpositions_have_the_same_key() {
+        let moved =
+            fen::from_fen("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1").unwrap();
+        let (_, repeated) = crate::pgn::from_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3").unwrap();
+        assert_eq!(moved.board.hash, repeated.board.hash);
+
+        let initial = fen::from_fen(fen::INITIAL_FEN).unwrap();
+        assert_ne!(moved.board.hash, initial.board.hash);
+    }
+}
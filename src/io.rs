@@ -1,9 +1,12 @@
-use crate::engine;
-use crate::engine::figures;
-use crate::GameState::{CheckMate, Normal};
-use colored;
+use chess_ai::ai;
+use chess_ai::ai::transposition::TranspositionTable;
+use chess_ai::engine;
+use chess_ai::engine::bitboard;
+use chess_ai::engine::figures;
+use chess_ai::pgn;
 use colored::Colorize;
 use std::io::Write;
+use std::time::Duration;
 
 /// Zug-Funktion für Menschen.
 ///
@@ -12,17 +15,21 @@ use std::io::Write;
 /// * `black_figures`: [`engine::Positions`] | Die Positionen der schwarzen Figuren.
 /// * `white_figures`: [`engine::Positions`] | Die Positionen der weißen Figuren.
 /// * `white`: `true`, wenn der ziehende Spieler weiß ist.
+/// * `history`: `&[engine::Board]` | Alle vorherigen Spielbretter (für die Wiederholungsregel), das letzte ist das aktuelle Spielbrett.
 ///
-/// # Rückgabewert `engine::GameState`
-/// Entweder einen [`engine::GameState::Normal`] zurück, das das neue Spielbrett enthält, oder einen [`engine::GameState::CheckMate`] wenn der ziehende Spieler gewonnen hat.
+/// # Rückgabewert `(engine::GameState, engine::Move)`
+/// Entweder einen [`engine::GameState::Normal`] zurück, das das neue Spielbrett enthält, einen [`engine::GameState::CheckMate`] wenn der ziehende Spieler gewonnen hat,
+/// oder einen [`engine::GameState::Draw`] wenn das Spiel unentschieden ausgegangen ist.
+/// Dazu wird der gemachte Zug zurückgegeben.
 pub fn turn(
     board: engine::Board,
     black_figures: engine::Positions,
     white_figures: engine::Positions,
     white: bool,
-) -> engine::GameState {
-    // Der Rückgabewert des loops wird in `resulting_board` gespeichert. Siehe [`quick_rust_explanation: Rückgabe von Werten`] wie die Rückgabe von Werten in Rust funktioniert.
-    let resulting_board = loop {
+    history: &[engine::Board],
+) -> (engine::GameState, engine::Move) {
+    // Der Rückgabewert des loops wird in `resulting_board` und `r#move` gespeichert. Siehe [`quick_rust_explanation: Rückgabe von Werten`] wie die Rückgabe von Werten in Rust funktioniert.
+    let (resulting_board, r#move) = loop {
         // Der Input wird vom Spieler eingeholt
         let input = loop {
             if white {
@@ -46,7 +53,13 @@ pub fn turn(
             // z.B. `a1` wird zu `A1`
             let input = input.to_uppercase();
 
-            // Wenn das parsen erfolgreich war, wird der input in dem Format `(A1, B2)` zurückgegeben.
+            // Mit `eval` wird die Bewertung des Spielbretts aufgeschlüsselt, danach wird wieder nach einem Zug gefragt.
+            if input.trim() == "EVAL" {
+                print_evaluation(black_figures, white_figures);
+                continue;
+            }
+
+            // Wenn das parsen erfolgreich war, wird der input in dem Format `(A1, B2, Umwandlungsfigur)` zurückgegeben.
             // Ansonsten wird dem Spieler mitgeteilt, dass er eine falsche Angabe gemacht hat und der Prozess des Input Einholens beginnt von neuem.
             match parse_input(input) {
                 Some(value) => {
@@ -61,12 +74,12 @@ pub fn turn(
 
         // Wenn die zweite Koordinate des Inputs leer ist, bedeutet das, dass der Spieler nur über die möglichen Züge einer Figur bescheid wissen möchte.
         // Ansonsten möchte er eine Figur bewegen.
-        if input.1 == "" {
+        if input.1.is_empty() {
             handle_possible_moves_request(board, black_figures, white_figures, white, input.0);
             // Da der Spieler nun noch keinen Zug gemacht hat, wird der ganze Zug Prozess noch einmal von vorne gestartet.
             continue;
         } else {
-            // Wenn der Zug möglich war, wird das neue Spielbrett aus dem loop zurückgegeben.
+            // Wenn der Zug möglich war, wird das neue Spielbrett mit dem Zug aus dem loop zurückgegeben.
             // Ansonsten wird eine Error-Nachricht ausgegeben und der Zug Prozess beginnt noch einmal von vorne.
             match handle_move_request(board, black_figures, white_figures, white, input) {
                 Ok(value) => {
@@ -80,18 +93,221 @@ pub fn turn(
         };
     };
 
-    // Es wird überprüft ob der nicht ziehende Spieler nun Schach Matt ist und in diesem Fall wird ein [`GameState::CheckMate`] zurückgegeben.
-    // Ansonsten wird ein normaler status zurückgegeben. Beide Möglichkeiten enthalten das neue Spielfeld.
-    if engine::is_checkmate(
-        resulting_board.0,
-        resulting_board.1,
-        resulting_board.2,
-        !white,
-    ) {
-        CheckMate(resulting_board)
+    // Es wird überprüft ob der nicht ziehende Spieler nun Schach Matt ist (ein [`engine::GameState::CheckMate`]) oder das Spiel unentschieden ist (ein [`engine::GameState::Draw`]).
+    // Ansonsten wird ein normaler status zurückgegeben. Alle Möglichkeiten enthalten das neue Spielfeld.
+    (
+        engine::get_game_state(
+            resulting_board.0,
+            resulting_board.1,
+            resulting_board.2,
+            !white,
+            history,
+        ),
+        r#move,
+    )
+}
+
+/// Zug-Funktion für die KI. Der Fortschritt der Suche und der gefundene Zug werden ausgegeben.
+///
+/// # Parameter
+/// * `board`: [`engine::Board`] | Das Aktuelle Spielbrett.
+/// * `black_figures`: [`engine::Positions`] | Die Positionen der schwarzen Figuren.
+/// * `white_figures`: [`engine::Positions`] | Die Positionen der weißen Figuren.
+/// * `white`: `true`, wenn der ziehende Spieler weiß ist.
+/// * `time`: [`Duration`] | Wie lange die KI nachdenken darf.
+/// * `history`: `&[engine::Board]` | Alle vorherigen Spielbretter (für die Wiederholungsregel), das letzte ist das aktuelle Spielbrett.
+/// * `table`: [`TranspositionTable`] | Die Transpositionstabelle, die über alle Züge der KI behalten wird.
+/// * `config`: [`ai::SearchConfig`] | Die Einstellungen der Suche, z.B. mit wie vielen Threads gesucht wird und die Endspieltabellen, aus denen die KI ohne Suche zieht.
+/// * `book`: `Option<&ai::book::Book>` | Das Eröffnungsbuch, aus dem die KI ohne Suche zieht, solange die Stellung darin steht.
+///
+/// # Rückgabewert `(engine::GameState, engine::Move)`
+/// Siehe [`turn`].
+#[allow(clippy::too_many_arguments)]
+pub fn ai_turn(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+    time: Duration,
+    history: &[engine::Board],
+    table: &TranspositionTable,
+    config: &ai::SearchConfig,
+    book: Option<&ai::book::Book>,
+) -> (engine::GameState, engine::Move) {
+    table.reset_statistics();
+
+    // Die KI sucht immer tiefer, bis die Zeit um ist. Die Tiefe der letzten vollständigen Suche wird gespeichert.
+    let mut depth = 0;
+    // Die Kopfzeile des Fortschritts wird erst ausgegeben, wenn die KI sucht, also nicht bei einem Zug aus dem Eröffnungsbuch
+    // oder den Endspieltabellen.
+    let mut header = false;
+    let (state, best_move, best_value, source) = ai::turn(
+        board,
+        black_figures,
+        white_figures,
+        white,
+        time,
+        history,
+        table,
+        config,
+        book,
+        &mut |progress| {
+            if !header {
+                header = true;
+                if white {
+                    println!("       KDRRLLTTBBBBBBBB -> Fortschritt Bedeutung");
+                } else {
+                    println!("          KDRRLLTTBBBBBBBB -> Fortschritt Bedeutung");
+                }
+            }
+            print_progress(progress, white)
+        },
+        &mut |searched_depth, _, _| depth = searched_depth,
+    );
+
+    // Hatte die KI keinen Zug mehr, gibt es auch nichts auszugeben.
+    if best_move == engine::moves::NULL_MOVE {
+        println!();
+        return (state, best_move);
+    }
+
+    let (from_x, from_y) = bitboard::to_position(best_move.from);
+    let (to_x, to_y) = bitboard::to_position(best_move.to);
+
+    let best_board = match &state {
+        engine::GameState::Normal(value)
+        | engine::GameState::CheckMate(value)
+        | engine::GameState::Draw(value, _) => *value,
+    };
+
+    let figure = engine::get_figure(board, from_x, from_y);
+    println!(
+        "\nMindestens erreichbarer Wert: {}. \nAktueller Wert: {}\nBewegt {} von {}{} nach {}{}",
+        best_value,
+        ai::evaluate_board(best_board.1, best_board.2),
+        get_str(figure),
+        (b'A' + from_x - 1) as char,
+        from_y,
+        (b'A' + to_x - 1) as char,
+        to_y
+    );
+
+    if best_move.promotion != 0 {
+        println!(
+            "Wandelt {} in {} um",
+            get_str(figure),
+            get_str(figure - figures::PAWN + best_move.promotion)
+        );
+    }
+
+    match source {
+        ai::MoveSource::Book => {
+            println!("Zug aus dem Eröffnungsbuch");
+            return (state, best_move);
+        }
+        ai::MoveSource::Tablebases => {
+            println!("Zug aus den Endspieltabellen");
+            return (state, best_move);
+        }
+        ai::MoveSource::Search => (),
+    }
+
+    println!("Suchtiefe: {} Halbzüge", depth);
+
+    // Wie oft eine schon durchsuchte Stellung in der Transpositionstabelle gefunden wurde.
+    let (probes, hits) = table.get_statistics();
+    println!(
+        "Transpositionstabelle: {:.1} % Treffer ({} von {}), {} ‰ belegt",
+        hits as f64 * 100.0 / probes.max(1) as f64,
+        hits,
+        probes,
+        table.get_fullness()
+    );
+
+    (state, best_move)
+}
+
+/// Gibt den Fortschritt der KI als Balken aus, ein `#` für jede durchsuchte Figur.
+///
+/// # Parameter
+/// * `progress`: `u8` | Die Anzahl der durchsuchten Figuren (von 16).
+/// * `white`: `true`, wenn die KI weiß ist.
+fn print_progress(progress: u8, white: bool) {
+    if white {
+        print!("\rWeiß> [");
     } else {
-        Normal(resulting_board)
+        print!("\rSchwarz> [");
+    }
+
+    for i in 0..16 {
+        if i < progress {
+            print!("#");
+        } else {
+            print!(" ");
+        }
     }
+
+    print!("]");
+
+    std::io::stdout().flush().expect("Could not flush stdout!");
+}
+
+/// Gibt die Bewertung des Spielbretts als Tabelle aus: jeden Term für Weiß und Schwarz im Mittel- und Endspiel,
+/// die Spielphase und wie beide Werte zur Bewertung gemischt werden.
+///
+/// # Parameter
+/// * `black_figures`: [`engine::Positions`] | Positionen der schwarzen Figuren.
+/// * `white_figures`: [`engine::Positions`] | Positionen der weißen Figuren.
+pub fn print_evaluation(black_figures: engine::Positions, white_figures: engine::Positions) {
+    let [white, black] = ai::evaluation::get_terms(&black_figures, &white_figures);
+    let game_phase = ai::evaluation::get_game_phase(&black_figures, &white_figures);
+
+    let rows = [
+        ("Material", white.material, black.material),
+        ("Figurenfelder", white.piece_squares, black.piece_squares),
+        ("Bauernstruktur", white.pawn_structure, black.pawn_structure),
+        ("Beweglichkeit", white.mobility, black.mobility),
+        ("Läuferpaar", white.bishop_pair, black.bishop_pair),
+        ("Turmlinien", white.rook_files, black.rook_files),
+        ("Königssicherheit", white.king_safety, black.king_safety),
+    ];
+
+    println!(
+        "{:<18}{:>16}{:>16}{:>16}",
+        "", "Weiß", "Schwarz", "Weiß - Schwarz"
+    );
+    println!(
+        "{:<18}{:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+        "Term", "MG", "EG", "MG", "EG", "MG", "EG"
+    );
+    let print_row = |name: &str, white: ai::evaluation::Score, black: ai::evaluation::Score| {
+        let difference = white - black;
+        println!(
+            "{:<18}{:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            name, white.mg, white.eg, black.mg, black.eg, difference.mg, difference.eg
+        );
+    };
+    for (name, white, black) in rows {
+        print_row(name, white, black);
+    }
+    print_row("Summe", white.get_total(), black.get_total());
+
+    let total = white.get_total() - black.get_total();
+    println!();
+    println!(
+        "Spielphase: {} von 24 (Mittelspiel {}/24, Endspiel {}/24)",
+        game_phase,
+        game_phase,
+        24 - game_phase
+    );
+    println!(
+        "Bewertung: ({} * {} + {} * {}) / 24 = {} (aus Sicht von Weiß)",
+        total.mg,
+        game_phase,
+        total.eg,
+        24 - game_phase,
+        ai::evaluation::blend(total, game_phase)
+    );
 }
 
 /// Funktion, die alle möglichen Züge für eine Figur findet und in die Konsole ausgibt.
@@ -112,8 +328,8 @@ fn handle_possible_moves_request(
     // Konvertierung der `String`-Darstellung zu der `(u8, u8)`-Darstellung der Koordinate.
     let mut chars = input.chars();
     let position: (u8, u8) = (
-        chars.next().unwrap() as u8 - 'A' as u8 + 1,
-        chars.next().unwrap() as u8 - '1' as u8 + 1,
+        chars.next().unwrap() as u8 - b'A' + 1,
+        chars.next().unwrap() as u8 - b'1' + 1,
     );
 
     // Ausfiltern, falls an der gegebenen Position keine Figur ist, oder diese Figur dem ziehenden Spieler nicht gehört.
@@ -128,45 +344,12 @@ fn handle_possible_moves_request(
         return;
     }
 
-    // Durch alle validen Züge iterieren.
-    let mut moves = engine::get_valid_moves(board, figure, position.0, position.1);
-    for i in 0..moves.len() {
-        let mut subset = moves[i];
-        for i in 0..subset.len() {
-            let mut r#move = subset[i];
-
-            // Siehe engine::figures::MovementSet für eine Erklärung,
-            // warum die Suche in den MovementSubSets beendet werden kann, sobald ein Zug eine größere Koordinate als 8 besitzt.
-            if r#move.0 > 8 {
-                break;
-            }
-
-            let board = engine::move_figure(
-                board,
-                black_figures,
-                white_figures,
-                figure,
-                position.0,
-                position.1,
-                r#move.0 as u8,
-                r#move.1 as u8,
-                white,
-            );
-
-            // Falls das Board nicht valide ist (falls der König im nächsten Zug geschmissen werden könnte) wird die weiter Suche in diesem MovementSubSet beendet.
-            // Again: Siehe engine::figures::MovementSet für eine Erklärung.
-            if !engine::is_board_valid(board.0, board.1, board.2, white) {
-                r#move.0 = 127;
-                r#move.1 = 127;
-                subset[i] = r#move;
-                break;
-            }
-
-            subset[i] = r#move;
-        }
-
-        moves[i] = subset
-    }
+    // Die Zielpositionen aller legalen Züge der Figur werden gesammelt.
+    let from = bitboard::to_square(position.0, position.1);
+    let moves = engine::generate_legal_moves(board, black_figures, white_figures, white)
+        .iter()
+        .filter(|r#move| r#move.from == from)
+        .fold(0, |moves, r#move| moves | 1 << r#move.to);
 
     // Am ende werden die validen moves an die print methode weitergegeben.
     print_board_with_movements(board, moves, position.0, position.1);
@@ -179,30 +362,36 @@ fn handle_possible_moves_request(
 /// * `black_figures`: [`engine::Positions`] | Positionen der schwarzen Figuren.
 /// * `white_figures`: [`engine::Positions`] | Positionen der weißen Figuren.
 /// * `white` : `bool` | `true`, wenn weiß am Zug ist.
-/// * `input` : `(String, String)` | Validierter input in (von (als `String`), nach (als `String`)-Form.
+/// * `input` : `(String, String, figures::Figure)` | Validierter input in (von (als `String`), nach (als `String`), Umwandlungsfigur)-Form.
 ///
-/// # Rückgabewert `Result<(engine::Board, engine::Positions, engine::Positions), String>`
-/// → Siehe [`Result`], [`engine::Board`], [`engine::Positions`]
+/// # Rückgabewert `Result<((engine::Board, engine::Positions, engine::Positions), engine::Move), String>`
+/// → Siehe [`Result`], [`engine::Board`], [`engine::Positions`], [`engine::Move`]
 ///
-/// Gibt [`Result::Ok`] mit dem Spielbrett nach dem Zug oder [`Result::Err`] mit einer Fehlernachricht zurück.
+/// Gibt [`Result::Ok`] mit dem Spielbrett nach dem Zug und dem Zug oder [`Result::Err`] mit einer Fehlernachricht zurück.
 fn handle_move_request(
     board: engine::Board,
     black_figures: engine::Positions,
     white_figures: engine::Positions,
     white: bool,
-    input: (String, String),
-) -> Result<(engine::Board, engine::Positions, engine::Positions), String> {
+    input: (String, String, figures::Figure),
+) -> Result<
+    (
+        (engine::Board, engine::Positions, engine::Positions),
+        engine::Move,
+    ),
+    String,
+> {
     // Konvertierung der String-Darstellung zu der `(u8, u8)`-Darstellung der Koordinaten.
     let mut chars = input.0.chars();
     let start_position: (u8, u8) = (
-        chars.next().unwrap() as u8 - 'A' as u8 + 1,
-        chars.next().unwrap() as u8 - '1' as u8 + 1,
+        chars.next().unwrap() as u8 - b'A' + 1,
+        chars.next().unwrap() as u8 - b'1' + 1,
     );
 
     let mut chars = input.1.chars();
     let end_position: (u8, u8) = (
-        chars.next().unwrap() as u8 - 'A' as u8 + 1,
-        chars.next().unwrap() as u8 - '1' as u8 + 1,
+        chars.next().unwrap() as u8 - b'A' + 1,
+        chars.next().unwrap() as u8 - b'1' + 1,
     );
 
     // Die Figur, die bewegt werden soll.
@@ -218,34 +407,27 @@ fn handle_move_request(
         return Err("Diese Figur gehört nicht dir!".to_string());
     }
 
-    let moves = engine::get_valid_moves(board, figure, start_position.0, start_position.1);
-    let resulting_board = engine::move_figure(
-        board,
-        black_figures,
-        white_figures,
-        figure,
-        start_position.0,
-        start_position.1,
-        end_position.0,
-        end_position.1,
-        white,
-    );
-
-    // Validiert den Zug und gibt gegebenenfalls einen Error aus.
-    if {
-        // 1. Überprüft, dass die Position, an die der Spieler ziehen möchte, auch im MovementSet enthalten ist.
-        !engine::contains_position(moves, end_position)
-    } ||
-        // 2. Überprüft ob das Spielbrett valide ist, indem überprüft wird, ob der König im darauffolgenden Zug geschlagen werden könnte.
-        !engine::is_board_valid(resulting_board.0, resulting_board.1, resulting_board.2, white)
-    {
-        return Err(format!(
-            "Figur {} auf {} kann nicht nach {} bewegt werden!",
-            get_str(figure).on_bright_white(),
-            input.0,
-            input.1
-        ));
-    }
+    // Sucht den Zug unter den legalen Zügen. Ein Zug ist nur legal, wenn der eigene König im darauffolgenden Zug nicht geschlagen werden könnte.
+    // Bei einer Umwandlung muss auch die Umwandlungsfigur übereinstimmen.
+    let from = bitboard::to_square(start_position.0, start_position.1);
+    let to = bitboard::to_square(end_position.0, end_position.1);
+    let r#move = match engine::generate_legal_moves(board, black_figures, white_figures, white)
+        .iter()
+        .find(|r#move| {
+            r#move.from == from
+                && r#move.to == to
+                && (r#move.promotion == 0 || r#move.promotion == input.2)
+        }) {
+        Some(&r#move) => r#move,
+        None => {
+            return Err(format!(
+                "Figur {} auf {} kann nicht nach {} bewegt werden!",
+                get_str(figure).on_bright_white(),
+                input.0,
+                input.1
+            ))
+        }
+    };
 
     println!(
         "Bewege {} von {} nach {}",
@@ -254,7 +436,19 @@ fn handle_move_request(
         input.1
     );
 
-    Ok(resulting_board)
+    // Falls ein Bauer die letzte Reihe erreicht, wird er in die gewünschte Figur umgewandelt.
+    if r#move.promotion != 0 {
+        println!(
+            "Wandle {} in {} um",
+            get_str(figure).on_bright_white(),
+            get_str(figure - figures::PAWN + r#move.promotion).on_bright_white()
+        );
+    }
+
+    Ok((
+        engine::move_figure(board, black_figures, white_figures, r#move, white),
+        r#move,
+    ))
 }
 
 /// Parst den vom Spieler gegebenen Input mit der Gewünschten Aktion, falls möglich.
@@ -262,27 +456,56 @@ fn handle_move_request(
 /// # Parameter
 /// * `input`: Input vom Spieler
 ///
-/// # Rückgabewert `Option<(String, String)>`
+/// # Rückgabewert `Option<(String, String, figures::Figure)>`
 /// → Siehe [`Option`]
 ///
-/// Gibt den Input auf gespaltet in zwei Teile wieder.
-fn parse_input<'a>(input: String) -> Option<(String, String)> {
+/// Gibt den Input auf gespaltet in zwei Koordinaten und die Figur, in die ein Bauer umgewandelt werden soll, wieder.
+/// Ist keine Umwandlungsfigur angegeben, wird in eine Dame umgewandelt.
+fn parse_input(input: String) -> Option<(String, String, figures::Figure)> {
     let input: Vec<&str> = input.split_whitespace().collect();
 
     // Validiert den Input
-    if input.len() > 2
-        || input.len() < 1
+    if input.len() > 3
+        || input.is_empty()
         || !validate_coordinate(input[0])
-        || (input.len() == 2 && (!validate_coordinate(input[1]) || input[0] == input[1]))
+        || (input.len() >= 2 && (!validate_coordinate(input[1]) || input[0] == input[1]))
     {
         return None;
     }
 
+    // Die optionale dritte Angabe ist die Figur, in die ein Bauer umgewandelt werden soll.
+    let promotion = if input.len() == 3 {
+        parse_promotion(input[2])?
+    } else {
+        figures::QUEEN
+    };
+
     // Gibt je nach der Länge nur eine oder zwei Koordinaten zurück.
-    if input.len() == 2 {
-        Some((input[0].chars().collect(), input[1].chars().collect()))
+    if input.len() >= 2 {
+        Some((
+            input[0].chars().collect(),
+            input[1].chars().collect(),
+            promotion,
+        ))
     } else {
-        Some((input[0].chars().collect(), "".to_string()))
+        Some((input[0].chars().collect(), "".to_string(), promotion))
+    }
+}
+
+/// Parst die Figur, in die ein Bauer umgewandelt werden soll.
+///
+/// # Parameter
+/// * `promotion`: Der Buchstabe der Figur (`Q`, `R`, `B` oder `N`).
+///
+/// # Rückgabewert `Option<figures::Figure>`
+/// Gibt die farblose Figur zurück, falls der Buchstabe valide ist.
+fn parse_promotion(promotion: &str) -> Option<figures::Figure> {
+    match promotion {
+        "Q" => Some(figures::QUEEN),
+        "R" => Some(figures::ROOK),
+        "B" => Some(figures::BISHOP),
+        "N" => Some(figures::KNIGHT),
+        _ => None,
     }
 }
 
@@ -347,12 +570,12 @@ pub fn print_board(board: engine::Board) {
 ///
 /// # Parameter
 /// * `board`: Das [`engine::Board`], das ausgegeben werden soll.
-/// * `movements`: Das [`figures::MovementSet`], das markiert werden soll.
+/// * `movements`: Das [`bitboard::Bitboard`] mit den Positionen, die markiert werden sollen.
 /// * `aktive_x`: Die x-Koordinate der aktiven Figur.
 /// * `aktive_y`: Die y-Koordinate der aktiven Figur.
 pub fn print_board_with_movements(
     board: engine::Board,
-    movements: figures::MovementSet,
+    movements: bitboard::Bitboard,
     active_x: u8,
     active_y: u8,
 ) {
@@ -539,28 +762,17 @@ pub fn print_board_with_movements(
     println!("    A B C D E F G H");
 }
 
-/// Überprüft, ob eine Koordinate in einem MovementSet enthalten ist
+/// Überprüft, ob eine Koordinate in einem Bitboard enthalten ist
 ///
 /// # Parameter
-/// * `moves`: Das [`figures::MovementSet`] in dem der Zug enthalten sein soll.
+/// * `moves`: Das [`bitboard::Bitboard`] in dem der Zug enthalten sein soll.
 /// * `x`: Die x-Koordinate des Zuges.
 /// * `y`: Die y-Koordinate des Zuges.
 ///
 /// # Rückgabewert `bool`
-/// `true` falls der Zug im [`figures::MovementSet`] enthalten ist.
-fn is_in_movement_set(moves: figures::MovementSet, x: u8, y: u8) -> bool {
-    for subset in moves {
-        for position in subset {
-            if position.0 > 8 {
-                break;
-            }
-            if position.0 as u8 == x && position.1 as u8 == y {
-                return true;
-            }
-        }
-    }
-
-    return false;
+/// `true` falls der Zug im [`bitboard::Bitboard`] enthalten ist.
+fn is_in_movement_set(moves: bitboard::Bitboard, x: u8, y: u8) -> bool {
+    engine::contains_position(moves, (x, y))
 }
 
 /// Konvertiert die gegebene [`figures::Figure`] zu einem [`colored::ColoredString`].
@@ -589,3 +801,83 @@ pub fn get_str(figure: figures::Figure) -> colored::ColoredString {
 
     format!(" {}", r#char).black().on_bright_white()
 }
+
+/// Konvertiert den gegebenen [`engine::DrawReason`] zu einer Beschreibung.
+///
+/// # Parameter
+/// * `reason`: Der [`engine::DrawReason`], der konvertiert werden soll.
+///
+/// # Rückgabewert `&str`
+/// Gibt den Grund für das Remis als Text zurück.
+pub fn get_draw_reason_str(reason: &engine::DrawReason) -> &'static str {
+    match reason {
+        engine::DrawReason::Stalemate => "Patt",
+        engine::DrawReason::Repetition => "dreifache Stellungswiederholung",
+        engine::DrawReason::FiftyMoves => "die 50-Züge-Regel",
+        engine::DrawReason::InsufficientMaterial => "ungenügendes Material",
+    }
+}
+
+/// Spielt eine geladene Partie Zug für Zug ab.
+///
+/// Mit Enter wird der nächste Zug gezeigt, mit `z` der vorherige, mit einer Zahl wird zu diesem Halbzug gesprungen
+/// und mit `s` wird ab dem aktuellen Halbzug gegen die KI weitergespielt.
+///
+/// # Parameter
+/// * `game`: [`engine::Game`] | Die geladene Partie.
+///
+/// # Rückgabewert `usize`
+/// Gibt die Anzahl der Halbzüge zurück, nach denen weitergespielt werden soll.
+pub fn replay(game: &engine::Game) -> usize {
+    let plies = game.moves.len();
+    let mut ply = 0;
+
+    println!("Enter: nächster Zug | z: vorheriger Zug | <Zahl>: zu diesem Halbzug springen | s: ab hier gegen die KI spielen");
+
+    loop {
+        let current = engine::get_game_at(game, ply);
+        print_board(current.board);
+
+        if ply == 0 {
+            println!("Startstellung (0/{})", plies);
+        } else {
+            let before = engine::get_game_at(game, ply - 1);
+            println!(
+                "Halbzug {}/{}: {}",
+                ply,
+                plies,
+                pgn::get_san(
+                    before.board,
+                    before.black_figures,
+                    before.white_figures,
+                    before.white,
+                    game.moves[ply - 1]
+                )
+            );
+        }
+
+        print!("Replay> ");
+        std::io::stdout().flush().expect("Could not flush stdout!");
+
+        let mut input = "".to_string();
+        // Ist die Eingabe zu Ende, wird ab dem aktuellen Halbzug weitergespielt.
+        if std::io::stdin()
+            .read_line(&mut input)
+            .expect("Failed to read line!")
+            == 0
+        {
+            return ply;
+        }
+
+        match input.trim().to_uppercase().as_str() {
+            "" if ply < plies => ply += 1,
+            "" => println!("Die Partie ist zu Ende!"),
+            "Z" => ply = ply.saturating_sub(1),
+            "S" => return ply,
+            value => match value.parse::<usize>() {
+                Ok(value) if value <= plies => ply = value,
+                _ => println!("Eingabe ist falsch!"),
+            },
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! A chess engine with a min-max search.
+//!
+//! * [`engine`] contains the board, the rules and the move generation.
+//! * [`ai`] searches the best move and evaluates boards.
+//! * [`pgn`] reads and writes games in the Portable Game Notation.
+//!
+//! # Example
+//!
+//! ```
+//! use chess_ai::{ai, ai::transposition::TranspositionTable, engine, engine::bitboard};
+//!
+//! let game = engine::fen::from_fen("k7/7R/1K6/8/8/8/8/8 w - - 0 1").unwrap();
+//! let moves = engine::generate_legal_moves(game.board, game.black_figures, game.white_figures, game.white);
+//! assert_eq!(moves.len(), 20);
+//!
+//! let (_, best_move) = ai::search(
+//!     game.board,
+//!     game.black_figures,
+//!     game.white_figures,
+//!     game.white,
+//!     2,
+//!     &game.history,
+//!     &TranspositionTable::new(1),
+//!     &mut ai::ordering::Heuristics::new(),
+//!     &ai::SearchConfig::default(),
+//!     &mut |_| {},
+//! )
+//! .unwrap();
+//! assert_eq!(bitboard::to_position(best_move.to), (8, 8));
+//! ```
+
+pub mod ai;
+pub mod engine;
+pub mod pgn;
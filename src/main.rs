@@ -1,102 +1,410 @@
-use crate::engine::{Board, GameState};
-use std::time::Instant;
+use chess_ai::ai::transposition::{self, TranspositionTable};
+use chess_ai::engine::GameState;
+use chess_ai::{ai, engine, pgn};
+use std::time::{Duration, Instant};
 
-mod ai;
-mod engine;
 mod io;
+mod uci;
 
 ///
 /// Main function. Wird beim Programmstart aufgerufen.
 ///
+/// Mit `--fen <FEN>` kann das Spiel in einer beliebigen Stellung gestartet werden.
+/// Mit `--replay <Datei>` kann eine Partie aus einer PGN-Datei nachgespielt und ab einem beliebigen Halbzug fortgesetzt werden.
+/// Mit `--pgn <Datei>` wird angegeben, in welche Datei die Partie am Ende gespeichert wird.
+/// Mit `--time <Sekunden>` wird angegeben, wie lange die KI für jeden Zug nachdenken darf (Standard: 5 Sekunden).
+/// Mit `--threads <Anzahl>` wird angegeben, mit wie vielen Threads die KI sucht (Standard: alle Prozessorkerne).
+/// Mit `--book <Datei>` zieht die KI in der Eröffnung aus einem Eröffnungsbuch im Polyglot-Format, mit `--book-selection best` immer den besten Zug statt eines zufälligen.
+/// Mit `--hash <MB>` wird die Größe der Transpositionstabelle der KI angegeben (Standard: 64 MB).
+/// Mit `--syzygy <Verzeichnis>` spielt die KI Endspiele mit wenigen Figuren perfekt aus den Syzygy-Endspieltabellen im Verzeichnis.
+/// Das Lesen der offiziellen Syzygy-Dateien ist noch nicht überprüft, getestet wird nur mit selbst erzeugten Tabellen.
+///
+/// Mit `chess_ai uci` wird statt des Spiels das UCI-Protokoll gestartet, damit die KI in Schachprogrammen genutzt werden kann.
+/// Mit `chess_ai perft <Tiefe>` wird die Zuggenerierung getestet (optional mit `--divide` und `--fen <FEN>`).
+/// Mit `chess_ai bench [<Tiefe>]` wird die Geschwindigkeit der Suche gemessen.
+/// Mit `chess_ai eval [<FEN>]` wird die Bewertung einer Stellung aufgeschlüsselt ausgegeben.
+///
 fn main() {
-    // Initialisiert die `board` und `figures` Variablen, die die Informationen über das Spielbrett enthalten.
-    let mut board = engine::INITIAL_BOARD;
-    let mut black_figures = engine::POSITIONS_BLACK;
-    let mut white_figures = engine::POSITIONS_WHITE;
+    let book = match get_argument("--book") {
+        Some(path) => match ai::book::Book::open(&path, get_book_selection()) {
+            Ok(book) => Some(book),
+            Err(error) => {
+                println!("Das Eröffnungsbuch konnte nicht geladen werden: {}", error);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let tablebases = match get_argument("--syzygy") {
+        Some(directory) => match ai::tablebase::Tablebases::open(&directory) {
+            Ok(tablebases) => Some(tablebases),
+            Err(error) => {
+                println!(
+                    "Die Endspieltabellen konnten nicht geladen werden: {}",
+                    error
+                );
+                return;
+            }
+        },
+        None => None,
+    };
+
+    match std::env::args().nth(1).as_deref() {
+        Some("uci") => {
+            uci::run(book, tablebases);
+            return;
+        }
+        Some("perft") => {
+            perft();
+            return;
+        }
+        Some("bench") => {
+            bench();
+            return;
+        }
+        Some("eval") => {
+            eval();
+            return;
+        }
+        _ => {}
+    }
+
+    // Initialisiert die `game` Variable, die die Informationen über das Spielbrett enthält.
+    let mut game = if let Some(path) = get_argument("--replay") {
+        let loaded = match std::fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|pgn| pgn::from_pgn(&pgn))
+        {
+            Ok((tags, game)) => {
+                for (name, value) in tags {
+                    println!("{}: {}", name, value);
+                }
+                game
+            }
+            Err(error) => {
+                println!("Die Partie konnte nicht geladen werden: {}", error);
+                return;
+            }
+        };
+
+        let ply = io::replay(&loaded);
+        engine::get_game_at(&loaded, ply)
+    } else {
+        match get_argument("--fen") {
+            Some(fen) => match engine::fen::from_fen(&fen) {
+                Ok(game) => game,
+                Err(error) => {
+                    println!("Ungültige FEN: {}", error);
+                    return;
+                }
+            },
+            None => engine::new_game(),
+        }
+    };
 
-    let mut prev_boards = [board; 10];
+    let time = Duration::from_secs_f64(
+        get_argument("--time")
+            .and_then(|time| time.parse().ok())
+            .filter(|&time: &f64| time > 0.0)
+            .unwrap_or(5.0),
+    );
+
+    let config = ai::SearchConfig {
+        threads: get_argument("--threads")
+            .and_then(|threads| threads.parse().ok())
+            .or_else(|| {
+                std::thread::available_parallelism()
+                    .ok()
+                    .map(|threads| threads.get())
+            })
+            .unwrap_or(1)
+            .max(1),
+        tablebases: tablebases.as_ref(),
+        ..Default::default()
+    };
 
-    let mut fast_calc_counter = 0;
-    let mut search_depth = 3;
+    let table = TranspositionTable::new(
+        get_argument("--hash")
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(transposition::DEFAULT_SIZE),
+    );
 
     println!(
         "Start evaluation: {}",
-        ai::evaluate_board(black_figures, white_figures)
+        ai::evaluate_board(game.black_figures, game.white_figures)
     );
 
     // Der Rückgabewert des loops wird in `end_board` gespeichert. Siehe [`quick_rust_explanation: Rückgabe von Werten`] wie die Rückgabe von Werten in Rust funktioniert.
-    let end_board: (Board, bool) = loop {
-        io::print_board(board);
-
-        // `io::turn` ist die Zug-Funktion, wenn ein Mensch ziehen soll.
-        match io::turn(board, black_figures, white_figures, true) {
-            GameState::Normal(value) => {
-                board = value.0;
-                black_figures = value.1;
-                white_figures = value.2;
-            }
-            GameState::CheckMate(value) => {
-                break (value.0, true);
-            }
-        };
+    // Der zweite Wert ist `true`, wenn Weiß den letzten Zug gemacht hat.
+    // Ist die geladene Stellung schon das Ende des Spiels, wird gar nicht erst gezogen.
+    let end_board: (GameState, bool) = match engine::get_game_state(
+        game.board,
+        game.black_figures,
+        game.white_figures,
+        game.white,
+        &game.history[..game.history.len() - 1],
+    ) {
+        GameState::Normal(_) => loop {
+            io::print_board(game.board);
+
+            let (state, r#move) = if game.white {
+                // `io::turn` ist die Zug-Funktion, wenn ein Mensch ziehen soll.
+                io::turn(
+                    game.board,
+                    game.black_figures,
+                    game.white_figures,
+                    true,
+                    &game.history,
+                )
+            } else {
+                // `io::ai_turn` ist die Zug-Funktion, wenn die KI ziehen zoll.
+                io::ai_turn(
+                    game.board,
+                    game.black_figures,
+                    game.white_figures,
+                    false,
+                    time,
+                    &game.history,
+                    &table,
+                    &config,
+                    book.as_ref(),
+                )
+            };
 
-        io::print_board(board);
-
-        let time = Instant::now();
-
-        // `ai::turn` ist die Zug-Funktion, wenn die KI ziehen zoll.
-        match ai::turn(
-            board,
-            black_figures,
-            white_figures,
-            false,
-            search_depth,
-            prev_boards,
-        ) {
-            GameState::Normal(value) => {
-                board = value.0;
-                black_figures = value.1;
-                white_figures = value.2;
-
-                for i in (1..prev_boards.len()).rev() {
-                    prev_boards[i] = prev_boards[i - 1];
+            match state {
+                GameState::Normal(value) => engine::apply_turn(&mut game, value, r#move),
+                GameState::CheckMate(value) | GameState::Draw(value, _) => {
+                    // Hatte die KI keinen Zug mehr, gibt es auch keinen Zug, der gespeichert werden kann,
+                    // und den letzten Zug hat der andere Spieler gemacht.
+                    if r#move == engine::moves::NULL_MOVE {
+                        break (state, !game.white);
+                    }
+                    let white = game.white;
+                    engine::apply_turn(&mut game, value, r#move);
+                    break (state, white);
                 }
+            };
+        },
+        end_state => (end_state, !game.white),
+    };
 
-                prev_boards[0] = board;
-            }
-            GameState::CheckMate(value) => {
-                break (value.0, false);
-            }
-        };
+    let result = match end_board.0 {
+        GameState::Draw(..) => "1/2-1/2",
+        _ if end_board.1 => "1-0",
+        _ => "0-1",
+    };
 
-        let time = time.elapsed().as_millis();
-        if time < 2000 {
-            fast_calc_counter += 1;
-            if fast_calc_counter == 5 {
-                search_depth += 1;
-                println!("AI> Increasing search depth to {}!", search_depth);
-                fast_calc_counter = 0;
-            }
-        } else if time > 60000 {
-            fast_calc_counter = 0;
-            if search_depth > 3 {
-                search_depth -= 1;
-                println!("AI> Decreasing search depth to {}!", search_depth);
-            }
-        } else {
-            fast_calc_counter = 0;
+    match end_board.0 {
+        GameState::Draw(value, reason) => {
+            // Das letzte Spielbrett wird ausgegeben.
+            io::print_board(value.0);
+
+            // Und eine Nachricht mit dem Grund für das Remis wird ausgegeben.
+            println!("Remis durch {}!", io::get_draw_reason_str(&reason));
+        }
+        GameState::CheckMate(value) | GameState::Normal(value) => {
+            // Das Sieger-Spielbrett wird ausgegeben.
+            io::print_board(value.0);
+
+            // Und eine Nachricht mit dem Gewinner wird ausgegeben.
+            println!("WOW! {} hat gewonnen!", {
+                if end_board.1 {
+                    "Weiß"
+                } else {
+                    "Schwarz"
+                }
+            });
+        }
+    }
+
+    // Die Endstellung wird als FEN ausgegeben, damit sie später wieder geladen werden kann.
+    println!("FEN: {}", engine::fen::to_fen(&game));
+
+    // Die Partie wird als PGN gespeichert, damit sie in anderen Programmen angeschaut werden kann.
+    let path = get_argument("--pgn").unwrap_or_else(|| {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        format!("partie_{}.pgn", seconds)
+    });
+
+    match pgn::save(&path, &game, "Spieler", "chess_ai", result) {
+        Ok(()) => println!("Die Partie wurde in {} gespeichert.", path),
+        Err(error) => println!("Die Partie konnte nicht gespeichert werden: {}", error),
+    }
+}
+
+/// Zählt alle Stellungen, die mit der gegebenen Anzahl an Halbzügen erreicht werden können, und gibt sie aus.
+///
+/// Mit `--divide` wird die Anzahl für jeden Zug einzeln ausgegeben.
+fn perft() {
+    let depth = match std::env::args().nth(2).and_then(|depth| depth.parse().ok()) {
+        Some(depth) => depth,
+        None => {
+            println!("Benutzung: chess_ai perft <Tiefe> [--divide] [--fen <FEN>]");
+            return;
         }
     };
 
-    // Das Sieger-Spielbrett wird ausgegeben.
-    io::print_board(end_board.0);
+    let game = match engine::fen::from_fen(
+        &get_argument("--fen").unwrap_or_else(|| engine::fen::INITIAL_FEN.to_string()),
+    ) {
+        Ok(game) => game,
+        Err(error) => {
+            println!("Ungültige FEN: {}", error);
+            return;
+        }
+    };
+
+    let time = Instant::now();
 
-    // Und eine Nachricht mit dem Gewinner wird ausgegeben.
-    println!("WOW! {} hat gewonnen!", {
-        if end_board.1 {
-            "Weiß"
-        } else {
-            "Schwarz"
+    let nodes = if std::env::args().any(|arg| arg == "--divide") {
+        let divide = engine::perft::perft_divide(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            depth,
+        );
+        for (r#move, nodes) in &divide {
+            println!("{}: {}", uci::get_move_str(*r#move), nodes);
         }
-    });
+        println!("Züge: {}", divide.len());
+        divide.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        engine::perft::perft(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            depth,
+        )
+    };
+
+    let time = time.elapsed();
+    println!("Knoten: {}", nodes);
+    println!(
+        "Zeit: {} ms ({:.0} Knoten pro Sekunde)",
+        time.as_millis(),
+        nodes as f64 / time.as_secs_f64().max(0.001)
+    );
+}
+
+/// Die Stellungen, mit denen `bench` die Geschwindigkeit der Suche misst.
+const BENCH_FENS: [&str; 3] = [
+    engine::fen::INITIAL_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+/// Durchsucht einige feste Stellungen mit der gegebenen Tiefe (in Halbzügen, Standard: 4) und gibt die Knoten pro Sekunde aus.
+fn bench() {
+    let depth = std::env::args()
+        .nth(2)
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(4);
+
+    let mut total_nodes = 0;
+    let time = Instant::now();
+    let table = TranspositionTable::new(transposition::DEFAULT_SIZE);
+
+    for fen in BENCH_FENS {
+        let game = engine::fen::from_fen(fen).expect("Invalid benchmark FEN!");
+
+        // Jede Stellung wird mit einer leeren Tabelle durchsucht, damit die Reihenfolge keine Rolle spielt.
+        table.clear();
+        ai::NODES.store(0, std::sync::atomic::Ordering::Relaxed);
+        let start = Instant::now();
+        let result = ai::search(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            depth,
+            &game.history,
+            &table,
+            &mut ai::ordering::Heuristics::new(),
+            &ai::SearchConfig::default(),
+            &mut |_| {},
+        );
+        let nodes = ai::NODES.load(std::sync::atomic::Ordering::Relaxed);
+        total_nodes += nodes;
+
+        println!(
+            "{}: {} ({} Knoten, {} ms)",
+            fen,
+            result.map_or("-".to_string(), |(_, r#move)| uci::get_move_str(r#move)),
+            nodes,
+            start.elapsed().as_millis()
+        );
+    }
+
+    let time = time.elapsed();
+    println!("Knoten: {}", total_nodes);
+    println!(
+        "Zeit: {} ms ({:.0} Knoten pro Sekunde)",
+        time.as_millis(),
+        total_nodes as f64 / time.as_secs_f64().max(0.001)
+    );
+}
+
+/// Gibt die aufgeschlüsselte Bewertung der Stellung aus, die als FEN angegeben wird (Standard: die Anfangsstellung).
+///
+/// Die FEN kann wie bei [`get_argument`] in Anführungszeichen oder ohne angegeben werden.
+fn eval() {
+    let fen = std::env::args()
+        .skip(2)
+        .take_while(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let fen = if fen.is_empty() {
+        engine::fen::INITIAL_FEN
+    } else {
+        &fen
+    };
+
+    let game = match engine::fen::from_fen(fen) {
+        Ok(game) => game,
+        Err(error) => {
+            println!("Ungültige FEN: {}", error);
+            return;
+        }
+    };
+
+    io::print_board(game.board);
+    io::print_evaluation(game.black_figures, game.white_figures);
+}
+
+/// Liest mit `--book-selection`, wie die KI einen Zug aus dem Eröffnungsbuch auswählt.
+///
+/// # Rückgabewert `ai::book::Selection`
+/// [`ai::book::Selection::Best`] bei `best`, sonst [`ai::book::Selection::Weighted`].
+fn get_book_selection() -> ai::book::Selection {
+    match get_argument("--book-selection").as_deref() {
+        Some("best") => ai::book::Selection::Best,
+        _ => ai::book::Selection::Weighted,
+    }
+}
+
+/// Sucht den Wert einer Option in den Kommandozeilen-Argumenten.
+///
+/// Der Wert kann in Anführungszeichen (`--fen "<FEN>"`) oder ohne (`--fen <FEN>`) angegeben werden.
+///
+/// # Parameter
+/// * `name`: Der Name der Option, z.B. `--fen`.
+///
+/// # Rückgabewert `Option<String>`
+/// Gibt den Wert zurück, oder [`None`], wenn die Option nicht angegeben wurde.
+fn get_argument(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name).skip(1);
+
+    let mut fen = args.next()?;
+    for arg in args.take_while(|arg| !arg.starts_with("--")) {
+        fen.push(' ');
+        fen.push_str(&arg);
+    }
+
+    Some(fen)
 }
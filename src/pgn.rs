@@ -0,0 +1,584 @@
+use crate::engine;
+use crate::engine::bitboard;
+use crate::engine::fen;
+use crate::engine::figures;
+use crate::engine::moves;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Converts a move to the Standard Algebraic Notation (e.g. `Nbd7`, `exd6`, `O-O`, `e8=Q+`).
+///
+/// `board`, `black_figures` and `white_figures` describe the board before the move, `white` is `true` if white makes the move.
+pub fn get_san(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+    r#move: engine::Move,
+) -> String {
+    let (from_x, _) = bitboard::to_position(r#move.from);
+    let (to_x, to_y) = bitboard::to_position(r#move.to);
+    let figure = board.squares[r#move.from as usize];
+    let blank_figure = figures::colored_figure_to_blank_figure(figure);
+
+    let mut san = String::new();
+
+    if r#move.flags & moves::CASTLING != 0 {
+        san.push_str(if to_x > from_x { "O-O" } else { "O-O-O" });
+    } else {
+        let catches = r#move.flags & moves::CAPTURE != 0;
+
+        if blank_figure == figures::PAWN {
+            if catches {
+                san.push(get_file(from_x));
+            }
+        } else {
+            san.push(fen::figure_to_char(blank_figure));
+            san.push_str(&get_disambiguation(
+                board,
+                black_figures,
+                white_figures,
+                white,
+                r#move,
+            ));
+        }
+
+        if catches {
+            san.push('x');
+        }
+
+        san.push(get_file(to_x));
+        san.push((b'0' + to_y) as char);
+
+        if r#move.promotion != 0 {
+            san.push('=');
+            san.push(fen::figure_to_char(r#move.promotion));
+        }
+    }
+
+    let (board, black_figures, white_figures) =
+        engine::move_figure(board, black_figures, white_figures, r#move, white);
+
+    let king = if white {
+        black_figures[0]
+    } else {
+        white_figures[0]
+    };
+    if engine::is_position_attacked(board, king.1, king.2, white) {
+        if engine::has_valid_move(board, black_figures, white_figures, !white) {
+            san.push('+');
+        } else {
+            san.push('#');
+        }
+    }
+
+    san
+}
+
+/// Returns the file, rank or both of the start position, if another figure of the same kind could move to the same target position.
+fn get_disambiguation(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+    r#move: engine::Move,
+) -> String {
+    let (from_x, from_y) = bitboard::to_position(r#move.from);
+    let figure = board.squares[r#move.from as usize];
+
+    let others: Vec<(u8, u8)> =
+        engine::generate_legal_moves(board, black_figures, white_figures, white)
+            .iter()
+            .filter(|other| {
+                other.to == r#move.to
+                    && other.from != r#move.from
+                    && board.squares[other.from as usize] == figure
+            })
+            .map(|other| bitboard::to_position(other.from))
+            .collect();
+
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|other| other.0 != from_x) {
+        get_file(from_x).to_string()
+    } else if others.iter().all(|other| other.1 != from_y) {
+        from_y.to_string()
+    } else {
+        format!("{}{}", get_file(from_x), from_y)
+    }
+}
+
+fn get_file(x: u8) -> char {
+    (b'a' + x - 1) as char
+}
+
+/// Converts the game to the Portable Game Notation.
+///
+/// `result` is `1-0`, `0-1`, `1/2-1/2` or `*` if the game did not end yet.
+pub fn to_pgn(game: &engine::Game, white_player: &str, black_player: &str, result: &str) -> String {
+    let mut pgn = String::new();
+
+    pgn.push_str("[Event \"chess_ai\"]\n");
+    pgn.push_str("[Site \"?\"]\n");
+    pgn.push_str(&format!("[Date \"{}\"]\n", get_date()));
+    pgn.push_str("[Round \"?\"]\n");
+    pgn.push_str(&format!("[White \"{}\"]\n", white_player));
+    pgn.push_str(&format!("[Black \"{}\"]\n", black_player));
+    pgn.push_str(&format!("[Result \"{}\"]\n", result));
+
+    if game.start_fen != fen::INITIAL_FEN {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", game.start_fen));
+    }
+
+    pgn.push('\n');
+
+    // The start position is valid, because it was loaded into the game before.
+    let start = fen::from_fen(&game.start_fen).expect("The start position of the game is invalid!");
+    let (mut board, mut black_figures, mut white_figures) =
+        (start.board, start.black_figures, start.white_figures);
+    let mut white = start.white;
+    let mut fullmove = start.fullmove;
+
+    let mut tokens = Vec::new();
+    for (i, &r#move) in game.moves.iter().enumerate() {
+        if white {
+            tokens.push(format!("{}.", fullmove));
+        } else if i == 0 {
+            tokens.push(format!("{}...", fullmove));
+        }
+
+        tokens.push(get_san(board, black_figures, white_figures, white, r#move));
+
+        (board, black_figures, white_figures) =
+            engine::move_figure(board, black_figures, white_figures, r#move, white);
+
+        if !white {
+            fullmove += 1;
+        }
+        white = !white;
+    }
+    tokens.push(result.to_string());
+
+    // Lines of the movetext should not be longer than 80 characters.
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > 80 {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+
+    pgn
+}
+
+/// Writes the game as PGN to the file at `path`.
+pub fn save(
+    path: &str,
+    game: &engine::Game,
+    white_player: &str,
+    black_player: &str,
+    result: &str,
+) -> std::io::Result<()> {
+    std::fs::write(path, to_pgn(game, white_player, black_player, result))
+}
+
+/// Parses the first game of a text in the Portable Game Notation.
+///
+/// Comments, variations and numeric annotation glyphs are skipped. Every move is validated against the legal moves of the board.
+/// Returns the tag pairs and the game after the last move.
+pub fn from_pgn(pgn: &str) -> Result<(Vec<(String, String)>, engine::Game), String> {
+    let mut tags = Vec::new();
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+
+    let mut chars = pgn.chars();
+    while let Some(r#char) = chars.next() {
+        match r#char {
+            '[' if tokens.is_empty() && token.is_empty() => {
+                let mut tag = String::new();
+                let mut quoted = false;
+                let mut escaped = false;
+                for r#char in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if r#char == '\\' && quoted {
+                        escaped = true;
+                        continue;
+                    } else if r#char == '"' {
+                        quoted = !quoted;
+                        continue;
+                    } else if r#char == ']' && !quoted {
+                        break;
+                    }
+                    tag.push(r#char);
+                }
+
+                let tag = tag.trim();
+                let (name, value) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+                tags.push((name.to_string(), value.trim().to_string()));
+            }
+            '{' => {
+                chars.by_ref().find(|&r#char| r#char == '}');
+            }
+            ';' => {
+                chars.by_ref().find(|&r#char| r#char == '\n');
+            }
+            '(' => {
+                // Variations can contain variations and comments themselves.
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('(') => depth += 1,
+                        Some(')') => depth -= 1,
+                        Some('{') => {
+                            chars.by_ref().find(|&r#char| r#char == '}');
+                        }
+                        Some(_) => {}
+                        None => return Err("A variation is not closed".to_string()),
+                    }
+                }
+            }
+            _ if r#char.is_whitespace() || r#char == '$' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                // Numeric annotation glyphs like `$1` are read as a token and skipped below.
+                if r#char == '$' {
+                    token.push('$');
+                }
+            }
+            _ => token.push(r#char),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    let mut game = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => fen::from_fen(fen)?,
+        None => engine::new_game(),
+    };
+
+    for token in tokens {
+        // Move numbers like `12.` or `12...` can be written directly in front of the move.
+        let san = match token.rfind('.') {
+            Some(i) => &token[i + 1..],
+            None => &token,
+        };
+
+        if san.is_empty() || san.starts_with('$') {
+            continue;
+        }
+        if ["1-0", "0-1", "1/2-1/2", "*"].contains(&san) {
+            break;
+        }
+
+        let r#move = parse_san(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            san,
+        )
+        .map_err(|error| {
+            format!(
+                "Move {}{} '{}': {}",
+                game.fullmove,
+                if game.white { "." } else { "..." },
+                san,
+                error
+            )
+        })?;
+
+        let value = engine::move_figure(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            r#move,
+            game.white,
+        );
+        engine::apply_turn(&mut game, value, r#move);
+    }
+
+    Ok((tags, game))
+}
+
+/// Finds the legal move described by a move in the Standard Algebraic Notation.
+///
+/// `board`, `black_figures` and `white_figures` describe the board before the move, `white` is `true` if white makes the move.
+pub fn parse_san(
+    board: engine::Board,
+    black_figures: engine::Positions,
+    white_figures: engine::Positions,
+    white: bool,
+    san: &str,
+) -> Result<engine::Move, String> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let san = san.strip_suffix("e.p.").unwrap_or(san);
+
+    let legal_moves = engine::generate_legal_moves(board, black_figures, white_figures, white);
+
+    let candidates: Vec<engine::Move> = match san {
+        "O-O" | "0-0" | "O-O-O" | "0-0-0" => {
+            let to_x = if san.len() == 3 { 7 } else { 3 };
+            legal_moves
+                .iter()
+                .filter(|r#move| {
+                    r#move.flags & moves::CASTLING != 0
+                        && bitboard::to_position(r#move.to).0 == to_x
+                })
+                .copied()
+                .collect()
+        }
+        _ => {
+            let mut chars: Vec<char> = san
+                .chars()
+                .filter(|&r#char| !['x', ':', '-', '='].contains(&r#char))
+                .collect();
+
+            let blank_figure = match chars.first() {
+                Some('K') => figures::KING,
+                Some('Q') => figures::QUEEN,
+                Some('R') => figures::ROOK,
+                Some('B') => figures::BISHOP,
+                Some('N') => figures::KNIGHT,
+                _ => figures::PAWN,
+            };
+            if blank_figure != figures::PAWN {
+                chars.remove(0);
+            }
+
+            // A promotion follows the target position, e.g. `e8Q` (after the `=` was removed).
+            let promotion = match chars.as_slice() {
+                [.., rank, promotion] if rank.is_ascii_digit() && promotion.is_alphabetic() => {
+                    let promotion = fen::char_to_figure(promotion.to_ascii_uppercase())
+                        .filter(|&figure| figures::PROMOTION_FIGURES.contains(&figure))
+                        .ok_or("Unknown promotion figure")?;
+                    chars.pop();
+                    Some(promotion)
+                }
+                _ => None,
+            };
+
+            if chars.len() < 2 {
+                return Err("Not a move".to_string());
+            }
+            let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+            let (to_x, to_y) = fen::parse_position(&target).ok_or("Invalid target position")?;
+
+            let mut from_x = None;
+            let mut from_y = None;
+            for r#char in chars {
+                match r#char {
+                    'a'..='h' => from_x = Some(r#char as u8 - b'a' + 1),
+                    '1'..='8' => from_y = Some(r#char as u8 - b'0'),
+                    _ => return Err("Not a move".to_string()),
+                }
+            }
+
+            legal_moves
+                .iter()
+                .filter(|r#move| {
+                    let (move_from_x, move_from_y) = bitboard::to_position(r#move.from);
+                    figures::colored_figure_to_blank_figure(board.squares[r#move.from as usize])
+                        == blank_figure
+                        && r#move.to == bitboard::to_square(to_x, to_y)
+                        && from_x.is_none_or(|x| move_from_x == x)
+                        && from_y.is_none_or(|y| move_from_y == y)
+                        && (r#move.promotion == 0
+                            || r#move.promotion == promotion.unwrap_or(figures::QUEEN))
+                })
+                .copied()
+                .collect()
+        }
+    };
+
+    match candidates.as_slice() {
+        [r#move] => Ok(*r#move),
+        [] => Err("Not a legal move".to_string()),
+        _ => Err("The move is ambiguous".to_string()),
+    }
+}
+
+/// Returns the current date in the PGN format `YYYY.MM.DD`.
+fn get_date() -> String {
+    let days = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => (duration.as_secs() / 86400) as i64,
+        Err(_) => return "????.??.??".to_string(),
+    };
+
+    // Converts the days since 1970-01-01 to a date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The start position, the target position and the promotion figure of a move.
+    type Parsed = ((u8, u8), (u8, u8), figures::Figure);
+
+    /// Parses a single move on the board of the FEN.
+    fn parse(fen: &str, san: &str) -> Result<Parsed, String> {
+        let game = fen::from_fen(fen).unwrap();
+        parse_san(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+            san,
+        )
+        .map(|r#move| {
+            (
+                bitboard::to_position(r#move.from),
+                bitboard::to_position(r#move.to),
+                r#move.promotion,
+            )
+        })
+    }
+
+    #[test]
+    fn ambiguous_moves_are_told_apart() {
+        let fen = "4k3/8/8/8/8/4RN2/8/1N2R1K1 w - - 0 1";
+        assert_eq!(parse(fen, "Nbd2"), Ok(((2, 1), (4, 2), 0)));
+        assert_eq!(parse(fen, "Nfd2"), Ok(((6, 3), (4, 2), 0)));
+        assert_eq!(parse(fen, "R1e2"), Ok(((5, 1), (5, 2), 0)));
+        assert_eq!(parse(fen, "R3e2"), Ok(((5, 3), (5, 2), 0)));
+        assert_eq!(parse(fen, "Nd2"), Err("The move is ambiguous".to_string()));
+        assert_eq!(parse(fen, "Re2"), Err("The move is ambiguous".to_string()));
+    }
+
+    #[test]
+    fn promotions_and_castling_are_parsed() {
+        let fen = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+        assert_eq!(parse(fen, "e8=Q"), Ok(((5, 7), (5, 8), figures::QUEEN)));
+        assert_eq!(parse(fen, "e8=N"), Ok(((5, 7), (5, 8), figures::KNIGHT)));
+        assert_eq!(parse(fen, "e8"), Ok(((5, 7), (5, 8), figures::QUEEN)));
+        assert!(parse(fen, "e8=K").is_err());
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(parse(fen, "O-O"), Ok(((5, 1), (7, 1), 0)));
+        assert_eq!(parse(fen, "O-O-O"), Ok(((5, 1), (3, 1), 0)));
+        assert_eq!(parse(fen, "0-0-0"), Ok(((5, 1), (3, 1), 0)));
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        assert_eq!(parse(fen, "O-O"), Ok(((5, 8), (7, 8), 0)));
+        assert_eq!(parse(fen, "O-O-O"), Ok(((5, 8), (3, 8), 0)));
+    }
+
+    #[test]
+    fn annotations_are_skipped() {
+        let (tags, game) = from_pgn(
+            "[Event \"Test \\\"1\\\"\"]\n[Site \"?\"]\n\n\
+             1. e4 {best by test} e5 $1 2. Nf3 (2. f4 exf4 (2... d5) {gambit}) Nc6?! ; line comment\n\
+             3. Bb5+? a6 4. Ba4 1-0 5. Bb3",
+        )
+        .unwrap();
+
+        assert_eq!(
+            tags,
+            vec![
+                ("Event".to_string(), "Test \"1\"".to_string()),
+                ("Site".to_string(), "?".to_string())
+            ]
+        );
+        assert_eq!(game.moves.len(), 7);
+        assert_eq!(
+            fen::to_fen(&game),
+            "r1bqkbnr/1ppp1ppp/p1n5/4p3/B3P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 1 4"
+        );
+    }
+
+    #[test]
+    fn checks_and_checkmates_are_parsed() {
+        let (_, game) = from_pgn("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0").unwrap();
+        assert_eq!(game.moves.len(), 7);
+        assert!(matches!(
+            engine::get_game_state(
+                game.board,
+                game.black_figures,
+                game.white_figures,
+                game.white,
+                &game.history
+            ),
+            engine::GameState::CheckMate(_)
+        ));
+    }
+
+    #[test]
+    fn malformed_games_are_errors() {
+        for pgn in [
+            "1. e5",
+            "1. e4 e5 2. Ke3",
+            "1. Zf3",
+            "1. e4 (1. d4",
+            "[FEN \"8/8/8\"]",
+            "1. e4 e5 2. x",
+            "1. e9",
+            "1. Nf3 Nf6 2. Ng1 Ng8 3. O-O",
+            "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# Ke7",
+        ] {
+            assert!(from_pgn(pgn).is_err(), "{}", pgn);
+        }
+
+        for san in [
+            "", "x", "=", "+", "N", "Nx", "e8=", "a0", "Qh9", "é4", "O-O-O-O",
+        ] {
+            assert!(parse(fen::INITIAL_FEN, san).is_err(), "{}", san);
+        }
+    }
+
+    /// Writes the game as PGN, checks the movetext and the line lengths, and reads it again.
+    fn check_round_trip(game: &engine::Game, movetext: &str) {
+        let pgn = to_pgn(game, "White", "Black", "*");
+
+        assert!(pgn.lines().all(|line| line.len() <= 80), "{}", pgn);
+        let written = pgn.split("\n\n").nth(1).unwrap().replace('\n', " ");
+        assert_eq!(written.trim(), movetext);
+
+        let (_, loaded) = from_pgn(&pgn).unwrap();
+        assert_eq!(loaded.moves, game.moves);
+        assert_eq!(loaded.board, game.board);
+        assert_eq!(loaded.start_fen, game.start_fen);
+    }
+
+    #[test]
+    fn games_survive_a_round_trip() {
+        // The opera game: disambiguation, castling, checks, a checkmate and more than one line of movetext.
+        let movetext = "1. e4 e5 2. Nf3 d6 3. d4 Bg4 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7 \
+                        8. Nc3 c6 9. Bg5 b5 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 \
+                        14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# *";
+        let (_, game) = from_pgn(movetext).unwrap();
+        check_round_trip(&game, movetext);
+
+        // Disambiguation by the rank, a promotion and a game that does not start with the initial board.
+        let movetext = "1. R1a3 Kg7 2. e8=Q Kf6 3. Qe6+ Kg7 4. dxc8=N *";
+        let (_, game) = from_pgn(&format!(
+            "[SetUp \"1\"]\n[FEN \"2r4k/3PP3/8/R7/8/8/8/R3K3 w - - 0 1\"]\n\n{}",
+            movetext
+        ))
+        .unwrap();
+        check_round_trip(&game, movetext);
+    }
+}
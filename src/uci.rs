@@ -0,0 +1,340 @@
+use chess_ai::ai;
+use chess_ai::ai::tablebase::Tablebases;
+use chess_ai::ai::transposition::{self, TranspositionTable};
+use chess_ai::engine;
+use chess_ai::engine::bitboard;
+use chess_ai::engine::fen;
+use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Speaks the Universal Chess Interface over stdin and stdout until `quit` is received.
+///
+/// See https://www.wbec-ridderkerk.nl/html/UCIProtocol.html for the protocol.
+/// As long as the board is in the opening `book`, its moves are played without searching.
+/// Boards in the endgame `tablebases` are played from the tables, the tables can also be set with the option `SyzygyPath`.
+pub fn run(book: Option<ai::book::Book>, tablebases: Option<Tablebases>) {
+    let mut game = engine::new_game();
+    let mut search: Option<Search> = None;
+    let mut table = Arc::new(TranspositionTable::new(transposition::DEFAULT_SIZE));
+    let mut config = ai::SearchConfig::default();
+    let mut tablebases = tablebases.map(Arc::new);
+
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("uci") => {
+                println!("id name chess_ai");
+                println!("id author habetuz");
+                println!(
+                    "option name Hash type spin default {} min 1 max 4096",
+                    transposition::DEFAULT_SIZE
+                );
+                println!("option name Threads type spin default 1 min 1 max 256");
+                println!("option name SyzygyPath type string default <empty>");
+                for (name, _) in get_switches(&mut config) {
+                    println!("option name {} type check default true", name);
+                }
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => {
+                stop(&mut search);
+                let tokens: Vec<&str> = tokens.collect();
+                match tokens[..] {
+                    ["name", "Hash", "value", size] => match size.parse::<usize>() {
+                        Ok(size) => table = Arc::new(TranspositionTable::new(size.clamp(1, 4096))),
+                        Err(_) => println!("info string Invalid hash size {}", size),
+                    },
+                    ["name", "Threads", "value", threads] => match threads.parse::<usize>() {
+                        Ok(threads) => config.threads = threads.clamp(1, 256),
+                        Err(_) => println!("info string Invalid thread count {}", threads),
+                    },
+                    ["name", "SyzygyPath", "value", ref path @ ..] => {
+                        let path = path.join(" ");
+                        tablebases = if path.is_empty() || path == "<empty>" {
+                            None
+                        } else {
+                            match Tablebases::open(&path) {
+                                Ok(value) => Some(Arc::new(value)),
+                                Err(error) => {
+                                    println!("info string Invalid Syzygy path {}: {}", path, error);
+                                    None
+                                }
+                            }
+                        };
+                    }
+                    ["name", name, "value", value] => {
+                        match get_switches(&mut config)
+                            .into_iter()
+                            .find(|(switch, _)| *switch == name)
+                        {
+                            Some((_, switch)) => *switch = value == "true",
+                            None => println!("info string Unknown option {}", name),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some("ucinewgame") => {
+                stop(&mut search);
+                table.clear();
+                game = engine::new_game();
+            }
+            Some("position") => {
+                stop(&mut search);
+                match parse_position(tokens.collect()) {
+                    Ok(value) => game = value,
+                    Err(error) => println!("info string {}", error),
+                }
+            }
+            Some("go") => {
+                stop(&mut search);
+                let book_move = book.as_ref().and_then(|book| {
+                    book.get_move(
+                        game.board,
+                        game.black_figures,
+                        game.white_figures,
+                        game.white,
+                    )
+                });
+                let tablebase_move = || {
+                    tablebases.as_ref().and_then(|tablebases| {
+                        tablebases.probe_root(
+                            game.board,
+                            game.black_figures,
+                            game.white_figures,
+                            game.white,
+                        )
+                    })
+                };
+
+                match book_move.or_else(|| tablebase_move().map(|(r#move, _)| r#move)) {
+                    Some(r#move) => println!("bestmove {}", get_move_str(r#move)),
+                    None => {
+                        search = Some(go(
+                            &game,
+                            table.clone(),
+                            config,
+                            tablebases.clone(),
+                            tokens.collect(),
+                        ))
+                    }
+                }
+            }
+            Some("stop") => stop(&mut search),
+            Some("quit") => break,
+            _ => {}
+        }
+    }
+
+    stop(&mut search);
+}
+
+/// A search running in its own thread.
+struct Search {
+    handle: thread::JoinHandle<()>,
+    /// The stop flag of the search, see [`ai::SearchConfig::stop`].
+    stop: Arc<AtomicBool>,
+}
+
+/// Stops the running search. The search prints its best move before it finishes.
+fn stop(search: &mut Option<Search>) {
+    if let Some(search) = search.take() {
+        search.stop.store(true, Ordering::Relaxed);
+        search.handle.join().expect("The search thread panicked!");
+    }
+}
+
+/// Parses the arguments of the `position` command: `startpos` or `fen <FEN>`, optionally followed by `moves <move> ...`.
+fn parse_position(tokens: Vec<&str>) -> Result<engine::Game, String> {
+    let moves_index = tokens
+        .iter()
+        .position(|&token| token == "moves")
+        .unwrap_or(tokens.len());
+
+    let mut game = match tokens.first() {
+        Some(&"startpos") => engine::new_game(),
+        Some(&"fen") => fen::from_fen(&tokens[1..moves_index].join(" "))?,
+        _ => return Err("Expected startpos or fen".to_string()),
+    };
+
+    for &token in tokens.iter().skip(moves_index + 1) {
+        let r#move = engine::generate_legal_moves(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            game.white,
+        )
+        .iter()
+        .copied()
+        .find(|&r#move| get_move_str(r#move) == token)
+        .ok_or_else(|| format!("Illegal move {}", token))?;
+
+        let value = engine::move_figure(
+            game.board,
+            game.black_figures,
+            game.white_figures,
+            r#move,
+            game.white,
+        );
+        engine::apply_turn(&mut game, value, r#move);
+    }
+
+    Ok(game)
+}
+
+/// Starts a search for the arguments of the `go` command in a new thread.
+/// The search looks up boards with few figures in the `tablebases`.
+fn go(
+    game: &engine::Game,
+    table: Arc<TranspositionTable>,
+    config: ai::SearchConfig<'static>,
+    tablebases: Option<Arc<Tablebases>>,
+    tokens: Vec<&str>,
+) -> Search {
+    let argument = |name: &str| -> Option<u64> {
+        let index = tokens.iter().position(|&token| token == name)?;
+        tokens.get(index + 1)?.parse().ok()
+    };
+
+    let infinite = tokens.contains(&"infinite");
+    let depth = argument("depth").map_or(ai::MAX_DEPTH, |depth| {
+        depth.clamp(1, ai::MAX_DEPTH as u64) as u8
+    });
+
+    let (time, increment) = if game.white {
+        (argument("wtime"), argument("winc"))
+    } else {
+        (argument("btime"), argument("binc"))
+    };
+
+    // Without a fixed time per move, a part of the remaining time is used.
+    let movetime = argument("movetime").or_else(|| {
+        time.map(|time| {
+            let moves_to_go = argument("movestogo").unwrap_or(30).max(1);
+            (time / moves_to_go + increment.unwrap_or(0) / 2).min(time / 2)
+        })
+    });
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let search_stop = stop.clone();
+
+    let (board, black_figures, white_figures, white) = (
+        game.board,
+        game.black_figures,
+        game.white_figures,
+        game.white,
+    );
+    let history = game.history.clone();
+
+    let handle = thread::spawn(move || {
+        let start = Instant::now();
+        let config = ai::SearchConfig {
+            tablebases: tablebases.as_deref(),
+            stop: Some(&search_stop),
+            ..config
+        };
+
+        let best_move = ai::iterative_deepening(
+            board,
+            black_figures,
+            white_figures,
+            white,
+            depth,
+            movetime.map(Duration::from_millis),
+            &history,
+            &table,
+            &config,
+            &mut |_| {},
+            &mut |depth, value, r#move| {
+                let moves = ai::get_principal_variation(
+                    board,
+                    black_figures,
+                    white_figures,
+                    white,
+                    r#move,
+                    &table,
+                    depth,
+                );
+                println!(
+                    "info depth {} score {} time {} hashfull {} pv {}",
+                    depth,
+                    get_score_str(value, white),
+                    start.elapsed().as_millis(),
+                    table.get_fullness(),
+                    moves
+                        .into_iter()
+                        .map(get_move_str)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )
+            },
+        );
+
+        // With `go infinite`, the best move must only be sent after `stop`, even if the search ended before.
+        while infinite && !search_stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        match best_move {
+            Some((_, best_move)) => println!("bestmove {}", get_move_str(best_move)),
+            None => println!("bestmove 0000"),
+        }
+    });
+
+    Search { handle, stop }
+}
+
+/// Converts a value of [`ai::min_max`] to the score of the player to move, either in centipawns or as moves until mate.
+fn get_score_str(value: i32, white: bool) -> String {
+    let value = if white { value } else { -value };
+
+    // The player is checkmate `plies` plies after the current board.
+    if value > i32::MAX - 1000 {
+        let plies = (i32::MAX - 100) - value;
+        format!("mate {}", ((plies + 1) / 2).max(1))
+    } else if value < i32::MIN + 1000 {
+        let plies = value - (i32::MIN + 100);
+        format!("mate -{}", (plies / 2).max(1))
+    } else {
+        format!("cp {}", value)
+    }
+}
+
+/// Returns the UCI options that switch the enhancements of the search on or off, with their names.
+fn get_switches<'a>(config: &'a mut ai::SearchConfig) -> [(&'static str, &'a mut bool); 4] {
+    [
+        ("PVS", &mut config.principal_variation_search),
+        ("NullMove", &mut config.null_move_pruning),
+        ("LMR", &mut config.late_move_reductions),
+        ("CheckExtensions", &mut config.check_extensions),
+    ]
+}
+
+/// Converts a move to the long algebraic notation of the UCI, e.g. `e2e4` or `e7e8q`.
+pub fn get_move_str(r#move: engine::Move) -> String {
+    let (from_x, from_y) = bitboard::to_position(r#move.from);
+    let (to_x, to_y) = bitboard::to_position(r#move.to);
+
+    let mut string = format!(
+        "{}{}{}{}",
+        (b'a' + from_x - 1) as char,
+        from_y,
+        (b'a' + to_x - 1) as char,
+        to_y
+    );
+
+    if r#move.promotion != 0 {
+        string.push(fen::figure_to_char(r#move.promotion).to_ascii_lowercase());
+    }
+
+    string
+}